@@ -0,0 +1,303 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use crossmath::prelude::*;
+use crossmath::Crossmath;
+
+#[cfg(feature = "tui")]
+mod tui;
+
+/// Generates, solves and checks crossmath puzzles.
+#[derive(Parser)]
+#[command(name = "crossmath", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generates a new puzzle
+    Generate {
+        #[command(flatten)]
+        puzzle: PuzzleArgs,
+        #[arg(short, long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+        /// Writes the puzzle in this file instead of the standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Prints the solution of a puzzle saved as JSON
+    Solve { puzzle: PathBuf },
+    /// Checks an answer, one value per cell separated by ';', against a puzzle saved as JSON
+    Check { puzzle: PathBuf, answer: PathBuf },
+    /// Writes a printable PDF book of puzzles, followed by their answers
+    Book {
+        /// Number of puzzles in the book
+        #[arg(short = 'n', long, default_value_t = 8)]
+        puzzles: u32,
+        /// Puzzles on each page, from 1 to 6
+        #[arg(short, long, default_value_t = 4)]
+        per_page: u32,
+        #[arg(short, long, default_value = "Crossmath")]
+        title: String,
+        #[command(flatten)]
+        generation: PuzzleArgs,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Plays a puzzle in the terminal
+    Play {
+        /// Puzzle saved as JSON, a new one is generated otherwise
+        #[arg(long)]
+        puzzle: Option<PathBuf>,
+        #[command(flatten)]
+        generation: PuzzleArgs,
+    },
+    /// Plays a puzzle in a full screen terminal interface
+    #[cfg(feature = "tui")]
+    Tui {
+        /// Puzzle saved as JSON, a new one is generated otherwise
+        #[arg(long)]
+        puzzle: Option<PathBuf>,
+        #[command(flatten)]
+        generation: PuzzleArgs,
+    },
+}
+
+#[derive(clap::Args)]
+struct PuzzleArgs {
+    #[arg(short, long, default_value_t = 6)]
+    equations: u32,
+    /// Numbers on the left of the equals sign of each equation
+    #[arg(long, default_value_t = 2)]
+    operands: u32,
+    #[arg(short, long)]
+    seed: Option<u64>,
+    #[arg(short, long, value_enum, default_value_t = Level::Medium)]
+    difficulty: Level,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Level {
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// The grid as seen by the player, and the hints
+    Text,
+    Json,
+    /// A share code to open in the browser
+    Share,
+    /// A picture to print
+    Svg,
+}
+
+impl From<Level> for Difficulty {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Easy => Difficulty::Easy,
+            Level::Medium => Difficulty::Medium,
+            Level::Hard => Difficulty::Hard,
+            Level::Expert => Difficulty::Expert,
+        }
+    }
+}
+
+impl PuzzleArgs {
+    fn generate(&self) -> Result<Crossmath, CrossmathError> {
+        let mut config = CrossmathConfig::from_difficulty(self.difficulty.into())
+            .equations(self.equations)
+            .operands(self.operands);
+        if let Some(seed) = self.seed {
+            config = config.seed(seed);
+        }
+        Crossmath::with_config(&config)
+    }
+}
+
+type CliResult = Result<ExitCode, Box<dyn std::error::Error>>;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Generate { puzzle, format, output } => generate(&puzzle, format, output),
+        Command::Solve { puzzle } => solve(&puzzle),
+        Command::Check { puzzle, answer } => check(&puzzle, &answer),
+        Command::Book { puzzles, per_page, title, generation, output } => {
+            book(puzzles, per_page, title, &generation, &output)
+        }
+        Command::Play { puzzle, generation } => play(puzzle, &generation),
+        #[cfg(feature = "tui")]
+        Command::Tui { puzzle, generation } => {
+            let crossmath = match puzzle {
+                Some(path) => load(&path),
+                None => generation.generate().map_err(Into::into),
+            };
+            crossmath.and_then(|crossmath| {
+                tui::play(crossmath)?;
+                Ok(ExitCode::SUCCESS)
+            })
+        }
+    };
+    result.unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        ExitCode::FAILURE
+    })
+}
+
+fn load(path: &PathBuf) -> Result<Crossmath, Box<dyn std::error::Error>> {
+    let json = fs::read_to_string(path).map_err(|error| format!("{} : {}", path.display(), error))?;
+    Ok(Crossmath::from_json(&json)?)
+}
+
+fn generate(args: &PuzzleArgs, format: Format, output: Option<PathBuf>) -> CliResult {
+    let crossmath = args.generate()?;
+    let text = match format {
+        Format::Text => format!(
+            "{}Hints : {:?}\nSeed : {}\n",
+            crossmath.to_ascii(&AsciiOptions::new().show_coordinates(true)),
+            crossmath.hints(),
+            crossmath.seed()
+        ),
+        Format::Json => crossmath.to_json()? + "\n",
+        Format::Share => crossmath.to_share_code() + "\n",
+        Format::Svg => crossmath.to_svg(&RenderOptions::new().show_seed(true)),
+    };
+    match output {
+        Some(path) => fs::write(&path, text).map_err(|error| format!("{} : {}", path.display(), error))?,
+        None => print!("{}", text),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn solve(path: &PathBuf) -> CliResult {
+    let crossmath = load(path)?;
+    let report = crossmath.solve(2);
+    let unique = report.is_unique();
+    let Some(solution) = report.solution else {
+        println!("This puzzle has no solution.");
+        return Ok(ExitCode::FAILURE);
+    };
+    print!("{}", crossmath.to_ascii(&AsciiOptions::new().show_coordinates(true).entries(solution)));
+    if !unique {
+        println!("This puzzle has more than one solution.");
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn check(puzzle: &PathBuf, answer: &PathBuf) -> CliResult {
+    let crossmath = load(puzzle)?;
+    let answer = fs::read_to_string(answer).map_err(|error| format!("{} : {}", answer.display(), error))?;
+    let report = crossmath.check(answer.trim().to_string())?;
+    if report.is_solved() {
+        println!("Solved !");
+        return Ok(ExitCode::SUCCESS);
+    }
+    println!("Completion : {:.0} %", report.completion());
+    println!("Empty cells : {:?}", report.empty_cells());
+    println!("Wrong cells : {:?}", report.wrong_cells());
+    println!("Violated equations : {:?}", report.violated_equations());
+    Ok(ExitCode::FAILURE)
+}
+
+fn book(puzzles: u32, per_page: u32, title: String, args: &PuzzleArgs, output: &PathBuf) -> CliResult {
+    let mut options = BookOptions::new()
+        .puzzles(puzzles)
+        .per_page(per_page)
+        .difficulty(args.difficulty.into())
+        .equations(args.equations)
+        .operands(args.operands)
+        .title(title);
+    if let Some(seed) = args.seed {
+        options = options.seed(seed);
+    }
+    let book = Book::generate(&options)?;
+    fs::write(output, book.to_pdf()).map_err(|error| format!("{} : {}", output.display(), error))?;
+    println!("{} puzzles written to {}", book.puzzles().len(), output.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+const PLAY_HELP: &str = "Commands :
+  set <x> <y> <value>   writes a value in a hidden cell
+  clear <x> <y>         empties a hidden cell
+  undo, redo            cancels or applies again the last change
+  hint                  points to the easiest cell to find
+  check                 tells whether the grid is solved
+  help, quit";
+
+fn play(puzzle: Option<PathBuf>, args: &PuzzleArgs) -> CliResult {
+    let crossmath = match puzzle {
+        Some(path) => load(&path)?,
+        None => args.generate()?,
+    };
+    let mut session = GameSession::new(crossmath);
+    let now = || std::time::SystemTime::UNIX_EPOCH.elapsed().map_or(0.0, |time| time.as_millis() as f64);
+    session.resume(now());
+    println!("{}", PLAY_HELP);
+
+    let stdin = io::stdin();
+    loop {
+        let entries = session
+            .hidden_cells()
+            .into_iter()
+            .filter_map(|idx| session.entry(idx as usize).map(|value| (idx as usize, value)));
+        print!("\n{}", session.crossmath().to_ascii(&AsciiOptions::new().show_coordinates(true).entries(entries)));
+        println!("Hints left : {:?}", session.remaining_hints());
+        print!("> ");
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(ExitCode::SUCCESS);
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let numbers: Vec<u32> = words.iter().skip(1).filter_map(|word| word.parse().ok()).collect();
+        let cell = |x: u32, y: u32| {
+            let grid = session.crossmath().grid();
+            (x < grid.width() && y < grid.height()).then(|| grid.get_idx(x, y))
+        };
+
+        let outcome = match (words.first().copied(), numbers.as_slice()) {
+            (Some("set"), &[x, y, value]) => match cell(x, y) {
+                Some(idx) => session.set_cell(idx, value).map_err(|error| error.to_string()),
+                None => Err("This cell is outside of the grid.".to_string()),
+            },
+            (Some("clear"), &[x, y]) => match cell(x, y) {
+                Some(idx) => session.clear_cell(idx).map_err(|error| error.to_string()),
+                None => Err("This cell is outside of the grid.".to_string()),
+            },
+            (Some("undo"), _) => session.undo().then_some(()).ok_or("Nothing to undo.".to_string()),
+            (Some("redo"), _) => session.redo().then_some(()).ok_or("Nothing to redo.".to_string()),
+            (Some("hint"), _) => match session.hint(HintTier::Equation) {
+                Some(hint) => {
+                    let (x, y) = session.crossmath().grid().coordinates(hint.cell);
+                    println!("Look at the cell ({}, {}), equation {}.", x, y, hint.equation.unwrap_or_default());
+                    Ok(())
+                }
+                None => Err("Every cell is already right.".to_string()),
+            },
+            (Some("check"), _) => {
+                let report = session.check();
+                if report.is_solved() {
+                    println!("Solved ! Score : {}", session.score(now()));
+                    return Ok(ExitCode::SUCCESS);
+                }
+                println!("{:.0} % filled, wrong cells : {:?}", report.completion(), report.wrong_cells());
+                Ok(())
+            }
+            (Some("quit"), _) => return Ok(ExitCode::SUCCESS),
+            _ => Err(PLAY_HELP.to_string()),
+        };
+        if let Err(message) = outcome {
+            println!("{}", message);
+        }
+    }
+}
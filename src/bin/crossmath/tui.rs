@@ -0,0 +1,294 @@
+use std::time::{Duration, SystemTime};
+
+use crossmath::prelude::*;
+use crossmath::Crossmath;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+const KEYS: &str = "Arrows : move   0-9 : write   Backspace : erase   u/r : undo/redo   \
+                    h : hint   Enter/c : check   q : quit";
+
+fn now() -> f64 {
+    SystemTime::UNIX_EPOCH.elapsed().map_or(0.0, |time| time.as_millis() as f64)
+}
+
+/// A puzzle played in the terminal, the cursor always stands on a hidden cell.
+struct App {
+    session: GameSession,
+    cursor: usize,
+    /** Cells pointed at by the last check */
+    wrong: Vec<u32>,
+    hint: Option<Hint>,
+    message: String,
+    solved: bool,
+}
+
+pub fn play(crossmath: Crossmath) -> std::io::Result<()> {
+    let cursor = crossmath.hidden_cells().first().map_or(0, |&idx| idx as usize);
+    let mut app = App {
+        session: GameSession::new(crossmath),
+        cursor,
+        wrong: vec![],
+        hint: None,
+        message: KEYS.to_string(),
+        solved: false,
+    };
+    app.session.resume(now());
+    ratatui::run(|terminal| app.run(terminal))
+}
+
+impl App {
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            // Wakes up every second to refresh the clock
+            if !event::poll(Duration::from_secs(1))? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                _ if self.solved => {}
+                KeyCode::Up => self.move_cursor(Direction::Up),
+                KeyCode::Down => self.move_cursor(Direction::Down),
+                KeyCode::Left => self.move_cursor(Direction::Left),
+                KeyCode::Right => self.move_cursor(Direction::Right),
+                KeyCode::Char(digit @ '0'..='9') => {
+                    let value = self.session.entry(self.cursor).unwrap_or(0);
+                    let value = value.saturating_mul(10).saturating_add(digit as u32 - '0' as u32);
+                    self.write(Some(value));
+                }
+                KeyCode::Backspace => {
+                    let value = self.session.entry(self.cursor).map(|value| value / 10);
+                    self.write(value.filter(|&value| value > 0));
+                }
+                KeyCode::Delete => self.write(None),
+                KeyCode::Char('u') => {
+                    self.session.undo();
+                }
+                KeyCode::Char('r') => {
+                    self.session.redo();
+                }
+                KeyCode::Char('h') => self.hint(),
+                KeyCode::Enter | KeyCode::Char('c') => self.check(),
+                _ => {}
+            }
+        }
+    }
+
+    fn write(&mut self, value: Option<u32>) {
+        let _ = match value {
+            Some(value) => self.session.set_cell(self.cursor, value),
+            None => self.session.clear_cell(self.cursor),
+        };
+    }
+
+    /** Jumps to the closest hidden cell in the given direction */
+    fn move_cursor(&mut self, direction: Direction) {
+        let grid = self.session.crossmath().grid();
+        let (x, y) = grid.coordinates(self.cursor);
+        let (x, y) = (x as i64, y as i64);
+        let target = self
+            .session
+            .crossmath()
+            .hidden_cells()
+            .into_iter()
+            .map(|idx| idx as usize)
+            .filter_map(|idx| {
+                let (other_x, other_y) = grid.coordinates(idx);
+                let (dx, dy) = (other_x as i64 - x, other_y as i64 - y);
+                let (ahead, aside) = match direction {
+                    Direction::Up => (-dy, dx),
+                    Direction::Down => (dy, dx),
+                    Direction::Left => (-dx, dy),
+                    Direction::Right => (dx, dy),
+                };
+                // Cells straight ahead first, then the ones slightly aside
+                (ahead > 0).then_some((ahead + 2 * aside.abs(), idx))
+            })
+            .min();
+        if let Some((_, idx)) = target {
+            self.cursor = idx;
+        }
+    }
+
+    /** Asking again for the same cell tells more about it */
+    fn hint(&mut self) {
+        let next = self.session.next_hint_cell();
+        let tier = match self.hint {
+            Some(Hint { tier: HintTier::Cell, cell, .. }) if Some(cell) == next => HintTier::Equation,
+            Some(Hint { tier: HintTier::Equation, cell, .. }) if Some(cell) == next => HintTier::Value,
+            _ => HintTier::Cell,
+        };
+        self.hint = self.session.hint(tier);
+        match self.hint {
+            Some(hint) => {
+                self.cursor = hint.cell;
+                self.message = match hint.tier {
+                    HintTier::Cell => "This cell can be found now.".to_string(),
+                    HintTier::Equation => "Look at the highlighted equation.".to_string(),
+                    HintTier::Value => "Here is its value.".to_string(),
+                };
+            }
+            None => self.message = "Every cell is already right.".to_string(),
+        }
+    }
+
+    fn check(&mut self) {
+        let report = self.session.check();
+        self.wrong = report.wrong_cells();
+        if report.is_solved() {
+            let now = now();
+            self.session.pause(now);
+            self.solved = true;
+            self.message = format!(
+                "Solved in {} ! Score : {}. Press q to quit.",
+                clock(self.session.elapsed(now)),
+                self.session.score(now)
+            );
+        } else {
+            self.message = format!(
+                "{:.0} % filled, {} wrong cell(s).",
+                report.completion(),
+                report.wrong_cells().len()
+            );
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [main, status] = Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(frame.area());
+        let [board, side] = Layout::horizontal([Constraint::Min(0), Constraint::Length(28)]).areas(main);
+
+        frame.render_widget(Paragraph::new(self.board()).block(Block::bordered().title(" Crossmath ")), board);
+
+        let consumed = self.session.consumed_mask();
+        let hints: Vec<Span> = self
+            .session
+            .crossmath()
+            .hints()
+            .into_iter()
+            .zip(consumed)
+            .map(|(hint, consumed)| {
+                let style = if consumed == 1 {
+                    Style::new().add_modifier(Modifier::DIM | Modifier::CROSSED_OUT)
+                } else {
+                    Style::new().add_modifier(Modifier::BOLD)
+                };
+                Span::styled(format!("{} ", hint), style)
+            })
+            .collect();
+        let side_text = vec![
+            Line::from(hints),
+            Line::default(),
+            Line::from(format!("Time : {}", clock(self.session.elapsed(now())))),
+            Line::from(format!("Hints used : {}", self.session.hints_used())),
+            Line::from(format!("Mistakes : {}", self.session.wrong_entries())),
+        ];
+        frame.render_widget(
+            Paragraph::new(side_text).wrap(Wrap { trim: true }).block(Block::bordered().title(" Hints ")),
+            side,
+        );
+        frame.render_widget(
+            Paragraph::new(self.message.as_str()).wrap(Wrap { trim: true }).block(Block::bordered()),
+            status,
+        );
+    }
+
+    /// The grid drawn with box-drawing characters, the cells written as in
+    /// `Crossmath::to_ascii`.
+    fn board(&self) -> Vec<Line<'static>> {
+        let crossmath = self.session.crossmath();
+        let grid = crossmath.grid();
+        let entries = (0..grid.len()).filter_map(|idx| self.session.entry(idx).map(|value| (idx, value)));
+        let options = AsciiOptions::new().placeholder(String::new()).entries(entries);
+        let size = crossmath.ascii_width(&options) + 2;
+        let highlighted: Vec<usize> = match self.hint.and_then(|hint| hint.equation) {
+            Some(equation) => crossmath.equations()[equation as usize].cells.clone(),
+            None => vec![],
+        };
+
+        let border = |left: &str, middle: &str, right: &str| {
+            let segments = vec!["─".repeat(size); grid.width() as usize];
+            Line::from(format!("{}{}{}", left, segments.join(middle), right))
+        };
+
+        let mut lines = vec![border("┌", "┬", "┐")];
+        for y in 0..grid.height() {
+            let mut spans = vec![Span::raw("│")];
+            for x in 0..grid.width() {
+                let idx = grid.get_idx(x, y);
+                let cell = grid[idx];
+                let text = crossmath.ascii_cell(idx, &options);
+
+                let mut style = Style::new();
+                if cell.is_hidden() {
+                    style = style.fg(Color::Cyan);
+                }
+                if highlighted.contains(&idx) {
+                    style = style.bg(Color::Yellow).fg(Color::Black);
+                }
+                if self.wrong.contains(&(idx as u32)) && self.session.entry(idx).is_some() {
+                    style = style.fg(Color::Red);
+                }
+                if idx == self.cursor && !self.solved {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                spans.push(Span::styled(format!("{:^size$}", text), style));
+                spans.push(Span::raw("│"));
+            }
+            lines.push(Line::from(spans));
+            if y + 1 < grid.height() {
+                lines.push(border("├", "┼", "┤"));
+            }
+        }
+        lines.push(border("└", "┴", "┘"));
+        lines
+    }
+}
+
+fn clock(milliseconds: f64) -> String {
+    let seconds = (milliseconds / 1000.0) as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn board_is_drawn_with_box_characters() {
+        let crossmath = Crossmath::from_seed(3, 4).unwrap();
+        let (width, height) = (crossmath.width() as usize, crossmath.height() as usize);
+        let mut app = App {
+            cursor: crossmath.hidden_cells()[0] as usize,
+            session: GameSession::new(crossmath),
+            wrong: vec![],
+            hint: None,
+            message: KEYS.to_string(),
+            solved: false,
+        };
+        app.move_cursor(Direction::Down);
+        app.write(Some(42));
+
+        let board = app.board();
+        assert_eq!(board.len(), 2 * height + 1);
+        let top = board[0].to_string();
+        assert!(top.starts_with('┌') && top.ends_with('┐'));
+        assert_eq!(top.matches('┬').count(), width - 1);
+        assert!(board.iter().any(|line| line.to_string().contains("42")));
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+    }
+}
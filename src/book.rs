@@ -0,0 +1,318 @@
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+
+use crate::prelude::*;
+use crate::Crossmath;
+
+/// A4, in points.
+const PAGE_WIDTH: f64 = 595.0;
+const PAGE_HEIGHT: f64 = 842.0;
+const MARGIN: f64 = 40.0;
+/// Room taken by the title at the top of a page and the number at its bottom.
+const HEADER: f64 = 40.0;
+const FOOTER: f64 = 30.0;
+/// Room taken by the label above each puzzle.
+const LABEL: f64 = 20.0;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+/// What goes in a book, see `Book::generate`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct BookOptions {
+    puzzles: u32,
+    per_page: u32,
+    difficulty: Difficulty,
+    equations: u32,
+    operands: u32,
+    seed: Option<u64>,
+    title: String,
+}
+
+impl Default for BookOptions {
+    fn default() -> Self {
+        Self {
+            puzzles: 8,
+            per_page: 4,
+            difficulty: Difficulty::default(),
+            equations: 6,
+            operands: 2,
+            seed: None,
+            title: "Crossmath".to_string(),
+        }
+    }
+}
+
+impl BookOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn puzzles(mut self, puzzles: u32) -> Self {
+        self.puzzles = puzzles.max(1);
+        self
+    }
+
+    /** Between 1 and 6 puzzles per page */
+    pub fn per_page(mut self, per_page: u32) -> Self {
+        self.per_page = per_page.clamp(1, 6);
+        self
+    }
+
+    pub fn difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+
+    pub fn equations(mut self, equations: u32) -> Self {
+        self.equations = equations;
+        self
+    }
+
+    pub fn operands(mut self, operands: u32) -> Self {
+        self.operands = operands;
+        self
+    }
+
+    /** Seed of the first puzzle, the next ones follow it */
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn title(mut self, title: String) -> Self {
+        self.title = title;
+        self
+    }
+}
+
+/// Puzzles laid out on printable pages, followed by their answers.
+pub struct Book {
+    title: String,
+    per_page: u32,
+    puzzles: Vec<Crossmath>,
+}
+
+impl Book {
+    pub fn new(title: String, per_page: u32, puzzles: Vec<Crossmath>) -> Self {
+        Self {
+            title,
+            per_page: per_page.clamp(1, 6),
+            puzzles,
+        }
+    }
+
+    pub fn generate(options: &BookOptions) -> Result<Self, CrossmathError> {
+        let first = options.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let puzzles = (0..options.puzzles as u64)
+            .map(|offset| {
+                let config = CrossmathConfig::from_difficulty(options.difficulty)
+                    .equations(options.equations)
+                    .operands(options.operands)
+                    .seed(first.wrapping_add(offset));
+                Crossmath::with_config(&config)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(options.title.clone(), options.per_page, puzzles))
+    }
+
+    pub fn puzzles(&self) -> &[Crossmath] {
+        &self.puzzles
+    }
+
+    pub fn to_pdf(&self) -> Vec<u8> {
+        let pages = self.puzzles.len().div_ceil(self.per_page as usize).max(1);
+        let total = 2 * pages;
+
+        let catalog = Ref::new(1);
+        let tree = Ref::new(2);
+        let regular = Ref::new(3);
+        let bold = Ref::new(4);
+        let page_ids: Vec<Ref> = (0..total as i32).map(|page| Ref::new(5 + 2 * page)).collect();
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog).pages(tree);
+        pdf.pages(tree).kids(page_ids.iter().copied()).count(total as i32);
+        pdf.type1_font(regular)
+            .base_font(Name(b"Helvetica"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+        pdf.type1_font(bold)
+            .base_font(Name(b"Helvetica-Bold"))
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+
+        for (number, &id) in page_ids.iter().enumerate() {
+            let answers = number >= pages;
+            let first = (number % pages) * self.per_page as usize;
+            let last = (first + self.per_page as usize).min(self.puzzles.len());
+            let title = if answers {
+                format!("{} : answers", self.title)
+            } else {
+                self.title.clone()
+            };
+
+            let mut content = Content::new();
+            text(&mut content, &title, MARGIN, MARGIN + 10.0, 16.0, BOLD, Anchor::Start);
+            let footer = format!("Page {} / {}", number + 1, total);
+            text(&mut content, &footer, PAGE_WIDTH / 2.0, PAGE_HEIGHT - MARGIN / 2.0, 9.0, REGULAR, Anchor::Middle);
+            for (slot, position) in (first..last).enumerate() {
+                self.draw_puzzle(&mut content, position, slot, answers);
+            }
+
+            let content_id = Ref::new(id.get() + 1);
+            let mut page = pdf.page(id);
+            page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH as f32, PAGE_HEIGHT as f32));
+            page.parent(tree);
+            page.contents(content_id);
+            page.resources().fonts().pair(REGULAR, regular).pair(BOLD, bold);
+            page.finish();
+            pdf.stream(content_id, &content.finish());
+        }
+        pdf.finish()
+    }
+
+    /** Draws a puzzle in the given slot of the page, scaled to fit in it */
+    fn draw_puzzle(&self, content: &mut Content, position: usize, slot: usize, answers: bool) {
+        let columns = if self.per_page == 1 { 1 } else { 2 };
+        let rows = self.per_page.div_ceil(columns);
+        let slot_width = (PAGE_WIDTH - 2.0 * MARGIN) / columns as f64;
+        let slot_height = (PAGE_HEIGHT - 2.0 * MARGIN - HEADER - FOOTER) / rows as f64;
+        let left = MARGIN + (slot % columns as usize) as f64 * slot_width;
+        let top = MARGIN + HEADER + (slot / columns as usize) as f64 * slot_height;
+
+        let crossmath = &self.puzzles[position];
+        let label = format!("#{} - {:?}", position + 1, crossmath.config().get_difficulty());
+        text(content, &label, left, top + LABEL / 2.0, 11.0, BOLD, Anchor::Start);
+
+        let options = RenderOptions::new()
+            .show_solution(answers)
+            .show_hints(!answers)
+            .show_seed(!answers);
+        let drawing = crossmath.drawing(&options);
+        let scale = (slot_width / drawing.width)
+            .min((slot_height - LABEL) / drawing.height)
+            .min(1.0);
+        draw(content, &drawing, left, top + LABEL, scale);
+    }
+}
+
+/// Writes the shapes of a drawing, its top left corner at (left, top) from the top left
+/// corner of the page.
+fn draw(content: &mut Content, drawing: &Drawing, left: f64, top: f64, scale: f64) {
+    for shape in &drawing.shapes {
+        match shape {
+            Shape::Rect { x, y, width, height, fill, stroke } => {
+                let (x, y) = (left + x * scale, top + y * scale);
+                let (width, height) = (width * scale, height * scale);
+                set_color(content, *fill, false);
+                content.rect(x as f32, (PAGE_HEIGHT - y - height) as f32, width as f32, height as f32);
+                match stroke {
+                    Some(stroke) => {
+                        set_color(content, *stroke, true);
+                        content.set_line_width(0.5);
+                        content.fill_nonzero_and_stroke();
+                    }
+                    None => {
+                        content.fill_nonzero();
+                    }
+                }
+            }
+            Shape::Text { x, y, size, content: label, color, bold, anchor } => {
+                set_color(content, *color, false);
+                let font = if *bold { BOLD } else { REGULAR };
+                text(content, label, left + x * scale, top + y * scale, size * scale, font, *anchor);
+            }
+        }
+    }
+    set_color(content, Color(0, 0, 0), false);
+}
+
+fn set_color(content: &mut Content, Color(r, g, b): Color, stroke: bool) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    if stroke {
+        content.set_stroke_rgb(r, g, b);
+    } else {
+        content.set_fill_rgb(r, g, b);
+    }
+}
+
+/** Writes a line of text, `y` being the middle of its height from the top of the page */
+fn text(content: &mut Content, label: &str, x: f64, y: f64, size: f64, font: Name, anchor: Anchor) {
+    let x = match anchor {
+        Anchor::Start => x,
+        Anchor::Middle => x - text_width(label, size) / 2.0,
+    };
+    let baseline = PAGE_HEIGHT - y - 0.35 * size;
+    content.begin_text();
+    content.set_font(font, size as f32);
+    content.next_line(x as f32, baseline as f32);
+    content.show(Str(&win_ansi(label)));
+    content.end_text();
+}
+
+/** Width of a text written in Helvetica, close enough to center numbers and operators */
+fn text_width(label: &str, size: f64) -> f64 {
+    let units: u32 = label
+        .chars()
+        .map(|character| match character {
+            '0'..='9' | '?' => 556,
+            '+' | '×' | '÷' | '=' => 584,
+            '-' => 333,
+            ' ' => 278,
+            _ => 600,
+        })
+        .sum();
+    units as f64 * size / 1000.0
+}
+
+/** The standard PDF fonts only know the WinAnsi encoding, close to Latin-1 */
+fn win_ansi(label: &str) -> Vec<u8> {
+    label
+        .chars()
+        .map(|character| match character {
+            '\u{20}'..='\u{7E}' | '\u{A0}'..='\u{FF}' => character as u8,
+            '–' => 0x96,
+            '—' => 0x97,
+            '’' => 0x92,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn books_hold_puzzles_and_answers() {
+        let options = BookOptions::new()
+            .puzzles(5)
+            .per_page(4)
+            .difficulty(Difficulty::Easy)
+            .equations(4)
+            .seed(30)
+            .title("Week 3".to_string());
+        let book = Book::generate(&options).unwrap();
+        assert_eq!(book.puzzles().len(), 5);
+        assert_eq!(book.puzzles()[1].seed(), 31);
+
+        let pdf = book.to_pdf();
+        let text = String::from_utf8_lossy(&pdf);
+        assert!(text.starts_with("%PDF-"));
+        assert!(text.contains("/Count 4"));
+        assert!(text.contains("(Page 1 / 4) Tj"));
+        assert!(text.contains("(Week 3 : answers) Tj"));
+        assert_eq!(text.matches("- Easy) Tj").count(), 10);
+
+        // Labels tell the difficulty asked for, not one guessed from the settings
+        let config = CrossmathConfig::from_difficulty(Difficulty::Hard).hidden_ratio(0.3).seed(4);
+        let book = Book::new("Custom".to_string(), 2, vec![Crossmath::with_config(&config).unwrap()]);
+        let text = String::from_utf8_lossy(&book.to_pdf()).into_owned();
+        assert_eq!(text.matches("- Hard) Tj").count(), 2);
+    }
+
+    #[test]
+    fn operators_are_encoded_for_pdf_fonts() {
+        assert_eq!(win_ansi("12 × 3 ÷ 4"), b"12 \xD7 3 \xF7 4");
+        assert_eq!(win_ansi("Été → 1"), b"\xC9t\xE9 ? 1");
+        assert!((text_width("12", 10.0) - 11.12).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+
+/// What is right and wrong in an answer, so that mistakes can be shown to the player.
+#[wasm_bindgen]
+#[derive(Clone, PartialEq, Debug)]
+pub struct CheckReport {
+    wrong_cells: Vec<u32>,
+    empty_cells: Vec<u32>,
+    satisfied_equations: Vec<u32>,
+    violated_equations: Vec<u32>,
+    incomplete_equations: Vec<u32>,
+    completion: f64,
+}
+
+#[wasm_bindgen]
+impl CheckReport {
+    /// Hidden cells holding a value outside of the hints left, or breaking an equation
+    /// with a value other than the one of the solution
+    pub fn wrong_cells(&self) -> Vec<u32> {
+        self.wrong_cells.clone()
+    }
+
+    /** Hidden cells the player has not filled yet */
+    pub fn empty_cells(&self) -> Vec<u32> {
+        self.empty_cells.clone()
+    }
+
+    /** Equations holding with the values given, by index in the equation list */
+    pub fn satisfied_equations(&self) -> Vec<u32> {
+        self.satisfied_equations.clone()
+    }
+
+    pub fn violated_equations(&self) -> Vec<u32> {
+        self.violated_equations.clone()
+    }
+
+    /** Equations with at least one empty cell */
+    pub fn incomplete_equations(&self) -> Vec<u32> {
+        self.incomplete_equations.clone()
+    }
+
+    /** Percentage of the hidden cells filled, between 0 and 100 */
+    pub fn completion(&self) -> f64 {
+        self.completion
+    }
+
+    pub fn is_solved(&self) -> bool {
+        self.empty_cells.is_empty() && self.wrong_cells.is_empty() && self.violated_equations.is_empty()
+    }
+}
+
+impl CheckReport {
+    /// Compares the value given to each cell of the grid with the hints and the equations.
+    /// Any answer using every hint once and making every equation hold is solved. In an
+    /// equation that doesn't hold, only the cells differing from the solution are wrong.
+    pub fn new(grid: &Grid, equations: &[PlacedEquation], values: &[Option<u32>]) -> Self {
+        let mut pool: BTreeMap<u32, u32> = BTreeMap::new();
+        for value in grid.iter().filter(|cell| cell.is_hidden()).filter_map(|cell| cell.value()) {
+            *pool.entry(value).or_insert(0) += 1;
+        }
+
+        let mut report = CheckReport {
+            wrong_cells: vec![],
+            empty_cells: vec![],
+            satisfied_equations: vec![],
+            violated_equations: vec![],
+            incomplete_equations: vec![],
+            completion: 100.0,
+        };
+
+        let hidden: Vec<usize> = (0..grid.len()).filter(|&idx| grid[idx].is_hidden()).collect();
+        // Right cells take their hint first, so that an over-used value is blamed on
+        // the cells differing from the solution
+        let (right, others): (Vec<usize>, Vec<usize>) =
+            hidden.iter().partition(|&&idx| values[idx].is_some() && values[idx] == grid[idx].value());
+        for idx in right.into_iter().chain(others) {
+            match values[idx].map(|value| pool.get_mut(&value)) {
+                None => report.empty_cells.push(idx as u32),
+                Some(Some(count)) if *count > 0 => *count -= 1,
+                Some(_) => report.wrong_cells.push(idx as u32),
+            }
+        }
+        report.empty_cells.sort_unstable();
+
+        for (position, eq) in equations.iter().enumerate() {
+            match eq.holds(grid, values) {
+                Some(true) => report.satisfied_equations.push(position as u32),
+                Some(false) => {
+                    report.violated_equations.push(position as u32);
+                    let wrong = eq
+                        .numbers()
+                        .into_iter()
+                        .filter(|&cell| grid[cell].is_hidden() && values[cell] != grid[cell].value());
+                    report.wrong_cells.extend(wrong.map(|cell| cell as u32));
+                }
+                None => report.incomplete_equations.push(position as u32),
+            }
+        }
+        report.wrong_cells.sort_unstable();
+        report.wrong_cells.dedup();
+
+        if !hidden.is_empty() {
+            let filled = hidden.len() - report.empty_cells.len();
+            report.completion = 100.0 * filled as f64 / hidden.len() as f64;
+        }
+        report
+    }
+}
+
+/// Value of each cell once the player's `;` separated answer is written in the grid.
+/// Shown numbers are kept, a hidden cell left empty has no value.
+pub(crate) fn parse_answer(grid: &Grid, answer: &str) -> Result<Vec<Option<u32>>, CrossmathError> {
+    let mut answer: Vec<&str> = answer.split(';').collect();
+    // A separator is usually written after the last cell too
+    if answer.len() == grid.len() + 1 && answer[grid.len()].trim().is_empty() {
+        answer.pop();
+    }
+    if answer.len() != grid.len() {
+        return Err(CrossmathError::AnswerLength {
+            expected: grid.len(),
+            found: answer.len(),
+        });
+    }
+
+    grid.iter()
+        .zip(answer)
+        .enumerate()
+        .map(|(position, (cell, answer))| match (cell, answer.trim()) {
+            (Cell::Number { hidden: true, .. }, "") => Ok(None),
+            (Cell::Number { hidden: true, .. }, value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| CrossmathError::InvalidAnswer { position, value: value.to_string() }),
+            _ => Ok(cell.value()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "5 + 7 = 12" written twice, on top of each other, with the given cells hidden
+    fn sample(hidden: &[usize]) -> (Grid, Vec<PlacedEquation>) {
+        let row = || Equation::new(5, 7, Operation::Plus).unwrap();
+        Grid::sample(5, 2, vec![(0, row()), (5, row())], hidden)
+    }
+
+    #[test]
+    fn answers_are_parsed_whatever_the_trailing_separator() {
+        let (grid, _) = sample(&[0]);
+        let expected = vec![Some(4), None, Some(7), None, Some(12), Some(5), None, Some(7), None, Some(12)];
+        assert_eq!(parse_answer(&grid, " 4;+;7;=;12;5;+;7;=;12;"), Ok(expected.clone()));
+        assert_eq!(parse_answer(&grid, "4;+;7;=;12;5;+;7;=;12"), Ok(expected));
+        assert_eq!(
+            parse_answer(&grid, "4;+;7;=;12"),
+            Err(CrossmathError::AnswerLength { expected: 10, found: 5 })
+        );
+        assert_eq!(
+            parse_answer(&grid, "four;+;7;=;12;5;+;7;=;12"),
+            Err(CrossmathError::InvalidAnswer { position: 0, value: "four".to_string() })
+        );
+    }
+
+    #[test]
+    fn report_points_at_mistakes() {
+        let (grid, equations) = sample(&[0, 2, 5]);
+        let values = parse_answer(&grid, "7;;5;;;;;;;;").unwrap();
+        let report = CheckReport::new(&grid, &equations, &values);
+        assert_eq!(report.empty_cells(), [5]);
+        assert_eq!(report.satisfied_equations(), [0]);
+        assert_eq!(report.incomplete_equations(), [1]);
+        assert!((report.completion() - 200.0 / 3.0).abs() < 1e-9);
+        assert!(!report.is_solved());
+
+        let values = parse_answer(&grid, "7;;7;;;4;;;;;").unwrap();
+        let report = CheckReport::new(&grid, &equations, &values);
+        assert_eq!(report.wrong_cells(), [0, 5]);
+        assert_eq!(report.violated_equations(), [0, 1]);
+        assert_eq!(report.completion(), 100.0);
+
+        // A value used twice is blamed on the cell where it doesn't belong
+        let (grid, equations) = sample(&[0, 4, 7]);
+        let values = parse_answer(&grid, "7;;;;;;;7;;;").unwrap();
+        let report = CheckReport::new(&grid, &equations, &values);
+        assert_eq!(report.wrong_cells(), [0]);
+        assert_eq!(report.satisfied_equations(), [1]);
+        assert_eq!(report.incomplete_equations(), [0]);
+
+        let (grid, equations) = sample(&[0, 2, 5]);
+        // The right value of a broken equation isn't a mistake
+        let values = parse_answer(&grid, "5;;3;;;5;;;;;").unwrap();
+        let report = CheckReport::new(&grid, &equations, &values);
+        assert_eq!(report.wrong_cells(), [2]);
+        assert_eq!(report.violated_equations(), [0]);
+
+        let values = parse_answer(&grid, "7;;5;;;5;;;;;").unwrap();
+        assert!(CheckReport::new(&grid, &equations, &values).is_solved());
+    }
+}
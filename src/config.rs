@@ -0,0 +1,367 @@
+use crate::prelude::*;
+
+/// Most numbers allowed on the left of the equals sign.
+pub const MAX_OPERANDS: u32 = 4;
+/// Upper bounds keeping the generation quick, and its arithmetic far from overflows.
+pub const MAX_EQUATIONS: u32 = 50;
+pub const MAX_OPERAND: u32 = 10_000;
+pub const MAX_RESULT: u32 = 100_000;
+pub const MAX_WEIGHT: u32 = 1_000;
+pub const MAX_GRID_SIDE: u32 = 1_000;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ConfigError {
+    NoEquation,
+    TooManyEquations(u32),
+    NullOperand,
+    OperandTooBig(u32),
+    ResultTooBig(u32),
+    WeightTooBig(u32),
+    EmptyOperandRange { min: u32, max: u32 },
+    ResultTooSmall { max_result: u32, max_operand: u32 },
+    NoOperator,
+    InvalidOperands(u32),
+    InvalidHiddenRatio(f64),
+    TooFewIntersections { max_intersections: u32, equations: u32 },
+    GridTooSmall { width: u32, height: u32, length: u32 },
+    GridTooBig { width: u32, height: u32 },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::NoEquation => {
+                write!(f, "Can't create less than 1 equation for the game, got 0.")
+            }
+            ConfigError::TooManyEquations(equations) => write!(
+                f,
+                "Can't create more than {} equations for the game, got {}.",
+                MAX_EQUATIONS, equations
+            ),
+            ConfigError::NullOperand => write!(f, "Operands must be at least 1."),
+            ConfigError::OperandTooBig(max) => {
+                write!(f, "Operands can't go above {}, got {}.", MAX_OPERAND, max)
+            }
+            ConfigError::ResultTooBig(max) => {
+                write!(f, "Results can't go above {}, got {}.", MAX_RESULT, max)
+            }
+            ConfigError::WeightTooBig(weight) => {
+                write!(f, "Operator weights can't go above {}, got {}.", MAX_WEIGHT, weight)
+            }
+            ConfigError::EmptyOperandRange { min, max } => write!(
+                f,
+                "The smallest operand ({}) is bigger than the biggest one ({}).",
+                min, max
+            ),
+            ConfigError::ResultTooSmall { max_result, max_operand } => write!(
+                f,
+                "The biggest result ({}) can't be smaller than the biggest operand ({}).",
+                max_result, max_operand
+            ),
+            ConfigError::NoOperator => write!(f, "At least one operator needs a positive weight."),
+            ConfigError::InvalidOperands(operands) => write!(
+                f,
+                "Equations need between 2 and {} operands, got {}.",
+                MAX_OPERANDS, operands
+            ),
+            ConfigError::InvalidHiddenRatio(ratio) => write!(
+                f,
+                "The proportion of hidden cells must be between 0 and 1, got {}.",
+                ratio
+            ),
+            ConfigError::TooFewIntersections { max_intersections, equations } => write!(
+                f,
+                "{} equations can't be linked with at most {} intersections per equation.",
+                equations, max_intersections
+            ),
+            ConfigError::GridTooSmall { width, height, length } => write!(
+                f,
+                "A {}x{} grid can't hold equations of {} cells.",
+                width, height, length
+            ),
+            ConfigError::GridTooBig { width, height } => write!(
+                f,
+                "A {}x{} grid is too big, its sides can't go above {}.",
+                width, height, MAX_GRID_SIDE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Everything needed to generate a puzzle, built step by step:
+///
+/// ```
+/// use crossmath::prelude::*;
+/// use crossmath::Crossmath;
+///
+/// let config = CrossmathConfig::new().equations(4).max_operand(20).seed(12);
+/// let crossmath = Crossmath::with_config(&config).unwrap();
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CrossmathConfig {
+    pub(crate) equations: u32,
+    /** The preset the settings started from, even once changed */
+    #[serde(default)]
+    pub(crate) difficulty: Difficulty,
+    pub(crate) settings: DifficultySettings,
+    pub(crate) max_width: Option<u32>,
+    pub(crate) max_height: Option<u32>,
+    pub(crate) seed: Option<u64>,
+}
+
+impl Default for CrossmathConfig {
+    fn default() -> Self {
+        Self::from_difficulty(Difficulty::default())
+    }
+}
+
+#[wasm_bindgen]
+impl CrossmathConfig {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_difficulty(difficulty: Difficulty) -> Self {
+        Self {
+            equations: 6,
+            difficulty,
+            settings: difficulty.settings(),
+            max_width: None,
+            max_height: None,
+            seed: None,
+        }
+    }
+
+    pub fn equations(mut self, number_of_equations: u32) -> Self {
+        self.equations = number_of_equations;
+        self
+    }
+
+    pub fn min_operand(mut self, min: u32) -> Self {
+        self.settings.min_number = min;
+        self
+    }
+
+    pub fn max_operand(mut self, max: u32) -> Self {
+        self.settings.max_number = max;
+        self
+    }
+
+    pub fn max_result(mut self, max: u32) -> Self {
+        self.settings.max_result = max;
+        self
+    }
+
+    /** Numbers on the left of the equals sign, from 2 to `MAX_OPERANDS` */
+    pub fn operands(mut self, operands: u32) -> Self {
+        self.settings.operands = operands;
+        self
+    }
+
+    /** A null weight forbids the operator */
+    pub fn operator_weights(mut self, plus: u32, minus: u32, times: u32, divide: u32) -> Self {
+        self.settings.operators = vec![
+            (Operation::Plus, plus),
+            (Operation::Minus, minus),
+            (Operation::Times, times),
+            (Operation::Divide, divide),
+        ];
+        self
+    }
+
+    pub fn hidden_ratio(mut self, ratio: f64) -> Self {
+        self.settings.hidden_ratio = ratio;
+        self
+    }
+
+    pub fn max_intersections(mut self, max: u32) -> Self {
+        self.settings.max_intersections = max;
+        self
+    }
+
+    pub fn chained_hidden(mut self, chained: bool) -> Self {
+        self.settings.chained_hidden = chained;
+        self
+    }
+
+    pub fn maximize_hidden(mut self, maximize: bool) -> Self {
+        self.settings.maximize_hidden = maximize;
+        self
+    }
+
+    pub fn max_width(mut self, width: u32) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    pub fn max_height(mut self, height: u32) -> Self {
+        self.max_height = Some(height);
+        self
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+}
+
+impl CrossmathConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let settings = &self.settings;
+        if self.equations == 0 {
+            return Err(ConfigError::NoEquation);
+        }
+        if self.equations > MAX_EQUATIONS {
+            return Err(ConfigError::TooManyEquations(self.equations));
+        }
+        if settings.min_number == 0 {
+            return Err(ConfigError::NullOperand);
+        }
+        if settings.max_number > MAX_OPERAND {
+            return Err(ConfigError::OperandTooBig(settings.max_number));
+        }
+        if settings.max_result > MAX_RESULT {
+            return Err(ConfigError::ResultTooBig(settings.max_result));
+        }
+        if let Some(&(_, weight)) = settings.operators.iter().find(|(_, weight)| *weight > MAX_WEIGHT) {
+            return Err(ConfigError::WeightTooBig(weight));
+        }
+        if settings.min_number > settings.max_number {
+            return Err(ConfigError::EmptyOperandRange {
+                min: settings.min_number,
+                max: settings.max_number,
+            });
+        }
+        if settings.max_result < settings.max_number {
+            return Err(ConfigError::ResultTooSmall {
+                max_result: settings.max_result,
+                max_operand: settings.max_number,
+            });
+        }
+        if settings.allowed_operators().next().is_none() {
+            return Err(ConfigError::NoOperator);
+        }
+        if !(2..=MAX_OPERANDS).contains(&settings.operands) {
+            return Err(ConfigError::InvalidOperands(settings.operands));
+        }
+        if !(0.0..=1.0).contains(&settings.hidden_ratio) {
+            return Err(ConfigError::InvalidHiddenRatio(settings.hidden_ratio));
+        }
+        let needed_intersections = if self.equations > 2 { 2 } else { self.equations - 1 };
+        if settings.max_intersections < needed_intersections {
+            return Err(ConfigError::TooFewIntersections {
+                max_intersections: settings.max_intersections,
+                equations: self.equations,
+            });
+        }
+        let too_big = |side: Option<u32>| side.is_some_and(|side| side > MAX_GRID_SIDE);
+        if too_big(self.max_width) || too_big(self.max_height) {
+            return Err(ConfigError::GridTooBig {
+                width: self.max_width.unwrap_or_default(),
+                height: self.max_height.unwrap_or_default(),
+            });
+        }
+        let (width, height) = self.grid_size();
+        let length = settings.equation_length();
+        let needed_height = if self.equations > 1 { length } else { 1 };
+        if width < length || height < needed_height {
+            return Err(ConfigError::GridTooSmall { width, height, length });
+        }
+        Ok(())
+    }
+
+    pub fn settings(mut self, settings: DifficultySettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn get_equations(&self) -> u32 {
+        self.equations
+    }
+
+    pub fn get_difficulty(&self) -> Difficulty {
+        self.difficulty
+    }
+
+    pub fn get_settings(&self) -> &DifficultySettings {
+        &self.settings
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /** Dimensions of the grid the equations are laid on, before cropping */
+    pub fn grid_size(&self) -> (u32, u32) {
+        let size = self.settings.equation_length().saturating_mul(self.equations).saturating_mul(2);
+        (
+            self.max_width.map_or(size, |width| width.min(size)),
+            self.max_height.map_or(size, |height| height.min(size)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation_explains_the_problem() {
+        assert_eq!(CrossmathConfig::new().validate(), Ok(()));
+        assert_eq!(CrossmathConfig::new().equations(0).validate(), Err(ConfigError::NoEquation));
+        assert_eq!(
+            CrossmathConfig::new().min_operand(30).max_operand(10).validate(),
+            Err(ConfigError::EmptyOperandRange { min: 30, max: 10 })
+        );
+        assert_eq!(
+            CrossmathConfig::new().operator_weights(0, 0, 0, 0).validate(),
+            Err(ConfigError::NoOperator)
+        );
+        assert_eq!(
+            CrossmathConfig::new().hidden_ratio(1.5).validate(),
+            Err(ConfigError::InvalidHiddenRatio(1.5))
+        );
+        assert_eq!(
+            CrossmathConfig::new().max_width(4).validate(),
+            Err(ConfigError::GridTooSmall { width: 4, height: 60, length: 5 })
+        );
+        assert_eq!(
+            CrossmathConfig::new().equations(u32::MAX).validate(),
+            Err(ConfigError::TooManyEquations(u32::MAX))
+        );
+        assert_eq!(
+            CrossmathConfig::new().max_operand(u32::MAX).max_result(u32::MAX).validate(),
+            Err(ConfigError::OperandTooBig(u32::MAX))
+        );
+        assert_eq!(
+            CrossmathConfig::new().max_result(MAX_RESULT + 1).validate(),
+            Err(ConfigError::ResultTooBig(MAX_RESULT + 1))
+        );
+        assert_eq!(
+            CrossmathConfig::new().operator_weights(1, u32::MAX, 0, 0).validate(),
+            Err(ConfigError::WeightTooBig(u32::MAX))
+        );
+        assert_eq!(
+            CrossmathConfig::new().max_height(u32::MAX).validate(),
+            Err(ConfigError::GridTooBig { width: 0, height: u32::MAX })
+        );
+        assert_eq!(
+            CrossmathConfig::new().operands(5).validate(),
+            Err(ConfigError::InvalidOperands(5))
+        );
+        assert_eq!(
+            CrossmathConfig::new().operands(3).max_width(6).validate(),
+            Err(ConfigError::GridTooSmall { width: 6, height: 84, length: 7 })
+        );
+        assert!(CrossmathConfig::new()
+            .max_intersections(1)
+            .validate()
+            .unwrap_err()
+            .to_string()
+            .contains("6 equations"));
+    }
+}
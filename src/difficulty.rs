@@ -0,0 +1,177 @@
+use crate::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+    Expert,
+}
+
+/// Every knob driving the generation of a puzzle.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DifficultySettings {
+    /// Smallest operand picked when completing an equation
+    pub min_number: u32,
+    /// Biggest operand picked when completing an equation
+    pub max_number: u32,
+    /// No term of an equation can go above this value
+    pub max_result: u32,
+    /// Numbers on the left of the equals sign of each equation
+    #[serde(default = "two")]
+    pub operands: u32,
+    /// Operators that can be used, with their relative weight
+    pub operators: Vec<(Operation, u32)>,
+    /// Proportion of the numbers hidden to the player
+    pub hidden_ratio: f64,
+    /// How many other equations can cross a single equation
+    pub max_intersections: u32,
+    /// Whether an equation can have more than one hidden number
+    pub chained_hidden: bool,
+    /// Hide as many numbers as possible while keeping a single solution,
+    /// whatever the hidden ratio
+    pub maximize_hidden: bool,
+}
+
+impl Difficulty {
+    pub fn settings(&self) -> DifficultySettings {
+        match self {
+            Difficulty::Easy => DifficultySettings {
+                min_number: 1,
+                max_number: 20,
+                max_result: 40,
+                operands: 2,
+                operators: vec![(Operation::Plus, 1), (Operation::Minus, 1)],
+                hidden_ratio: 0.3,
+                max_intersections: 2,
+                chained_hidden: false,
+                maximize_hidden: false,
+            },
+            Difficulty::Medium => DifficultySettings {
+                min_number: 1,
+                max_number: 99,
+                max_result: 200,
+                operands: 2,
+                operators: vec![
+                    (Operation::Plus, 1),
+                    (Operation::Minus, 1),
+                    (Operation::Times, 1),
+                    (Operation::Divide, 1),
+                ],
+                hidden_ratio: 0.4,
+                max_intersections: 3,
+                chained_hidden: true,
+                maximize_hidden: false,
+            },
+            Difficulty::Hard => DifficultySettings {
+                min_number: 2,
+                max_number: 99,
+                max_result: 300,
+                operands: 2,
+                operators: vec![
+                    (Operation::Plus, 1),
+                    (Operation::Minus, 1),
+                    (Operation::Times, 2),
+                    (Operation::Divide, 2),
+                ],
+                hidden_ratio: 0.5,
+                max_intersections: 3,
+                chained_hidden: true,
+                maximize_hidden: false,
+            },
+            Difficulty::Expert => DifficultySettings {
+                min_number: 2,
+                max_number: 199,
+                max_result: 1000,
+                operands: 2,
+                operators: vec![
+                    (Operation::Plus, 1),
+                    (Operation::Minus, 1),
+                    (Operation::Times, 2),
+                    (Operation::Divide, 2),
+                ],
+                hidden_ratio: 0.6,
+                max_intersections: 3,
+                chained_hidden: true,
+                maximize_hidden: false,
+            },
+        }
+    }
+}
+
+impl Difficulty {
+    /** Multiplies the score of a solved puzzle */
+    pub fn score_weight(&self) -> f64 {
+        match self {
+            Difficulty::Easy => 1.0,
+            Difficulty::Medium => 1.5,
+            Difficulty::Hard => 2.0,
+            Difficulty::Expert => 3.0,
+        }
+    }
+}
+
+/** Operands of the equations saved before they could have more */
+fn two() -> u32 {
+    2
+}
+
+impl Default for DifficultySettings {
+    fn default() -> Self {
+        Difficulty::default().settings()
+    }
+}
+
+impl DifficultySettings {
+    /** Cells taken by an equation : its operands, operators, equals sign and result */
+    pub fn equation_length(&self) -> u32 {
+        self.operands.saturating_mul(2).saturating_add(1)
+    }
+
+    pub fn random_operator(&self, rng: &mut impl Rng) -> Option<Operation> {
+        let total: u32 = self.operators.iter().map(|(_, weight)| weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0..total);
+        for (operator, weight) in &self.operators {
+            if pick < *weight {
+                return Some(*operator);
+            }
+            pick -= weight;
+        }
+        None
+    }
+
+    /** Operators with a non null weight, in declaration order */
+    pub fn allowed_operators(&self) -> impl Iterator<Item = Operation> + '_ {
+        self.operators
+            .iter()
+            .filter(|(_, weight)| *weight > 0)
+            .map(|(operator, _)| *operator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_operator_respects_weights() {
+        let settings = Difficulty::Easy.settings();
+        let mut rng = PuzzleRng::seed_from_u64(3);
+        for _ in 0..100 {
+            let operator = settings.random_operator(&mut rng);
+            assert!(matches!(operator, Some(Operation::Plus | Operation::Minus)));
+        }
+
+        let settings = DifficultySettings {
+            operators: vec![(Operation::Plus, 0), (Operation::Divide, 3)],
+            ..DifficultySettings::default()
+        };
+        assert_eq!(settings.random_operator(&mut rng), Some(Operation::Divide));
+        assert_eq!(settings.allowed_operators().collect::<Vec<_>>(), [Operation::Divide]);
+    }
+}
@@ -1,21 +1,97 @@
-use std::cmp::Ordering;
+use std::ops::Range;
 
 use crate::prelude::*;
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Operation {
     Plus,
     Minus,
+    Times,
+    Divide,
 }
 
-#[derive(Clone, Copy)]
+impl Operation {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Operation::Plus => "+",
+            Operation::Minus => "-",
+            Operation::Times => "×",
+            Operation::Divide => "÷",
+        }
+    }
+
+    /** x op y == y op x */
+    pub fn is_commutative(&self) -> bool {
+        matches!(self, Operation::Plus | Operation::Times)
+    }
+
+    /** x op y, as long as it is a positive integer */
+    pub fn apply(&self, x: u32, y: u32) -> Option<u32> {
+        let result = match self {
+            Operation::Plus => x.checked_add(y)?,
+            Operation::Minus => x.checked_sub(y)?,
+            Operation::Times => x.checked_mul(y)?,
+            Operation::Divide if y != 0 && x.is_multiple_of(y) => x / y,
+            Operation::Divide => return None,
+        };
+        (result > 0).then_some(result)
+    }
+
+    /** The x such that x op y = result */
+    pub fn solve_x(&self, y: u32, result: u32) -> Option<u32> {
+        let x = match self {
+            Operation::Plus => result.checked_sub(y)?,
+            Operation::Minus => result.checked_add(y)?,
+            Operation::Times if y != 0 && result.is_multiple_of(y) => result / y,
+            Operation::Times => return None,
+            Operation::Divide => result.checked_mul(y)?,
+        };
+        (self.apply(x, y) == Some(result)).then_some(x)
+    }
+
+    /** The y such that x op y = result */
+    pub fn solve_y(&self, x: u32, result: u32) -> Option<u32> {
+        let y = match self {
+            Operation::Plus => result.checked_sub(x)?,
+            Operation::Minus => x.checked_sub(result)?,
+            Operation::Times if x != 0 && result.is_multiple_of(x) => result / x,
+            Operation::Divide if result != 0 && x.is_multiple_of(result) => x / result,
+            Operation::Times | Operation::Divide => return None,
+        };
+        (self.apply(x, y) == Some(result)).then_some(y)
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum MatchParameter {
     X,
     Y,
     Result,
 }
 
-#[derive(Clone, Copy)]
+impl MatchParameter {
+    /** Index of the term in "x op y = result" */
+    pub fn term(&self) -> usize {
+        match self {
+            MatchParameter::X => 0,
+            MatchParameter::Y => 1,
+            MatchParameter::Result => 2,
+        }
+    }
+
+    pub fn from_term(term: usize) -> Option<Self> {
+        match term {
+            0 => Some(MatchParameter::X),
+            1 => Some(MatchParameter::Y),
+            2 => Some(MatchParameter::Result),
+            _ => None,
+        }
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Direction {
     Up,
     Down,
@@ -23,236 +99,408 @@ pub enum Direction {
     Right,
 }
 
-// x +/- y = result
+// a op b op c ... = result
+// Terms are numbered from the first operand, the result being the last one.
 pub struct Equation {
-    x: u32,
-    y: u32,
-    operator: Operation,
+    operands: Vec<u32>,
+    operators: Vec<Operation>,
     result: u32,
 }
 
 impl Equation {
-    pub fn new(x: u32, y: u32, operator: Operation) -> Self {
-        let (result, x, y) = get_result(x, y, &operator);
-        Self {
-            x,
-            y,
-            operator,
+    pub fn new(x: u32, y: u32, operator: Operation) -> Result<Self, CrossmathError> {
+        let (result, x, y) = get_result(x, y, &operator)?;
+        Ok(Self {
+            operands: vec![x, y],
+            operators: vec![operator],
             result,
-        }
+        })
     }
 
-    pub fn random() -> Self {
-        let mut rng = rand::thread_rng();
-        let x = rng.gen_range(1..MAX_NUMBER);
-        let y = rng.gen_range(1..MAX_NUMBER);
-        let operator = get_random_operator();
-        let (result, x, y) = get_result(x, y, &operator);
-        Self {
-            x,
-            y,
-            operator,
+    /// An equation of any length, products and divisions being computed before
+    /// additions and subtractions.
+    pub fn chain(operands: Vec<u32>, operators: Vec<Operation>) -> Result<Self, CrossmathError> {
+        let result = evaluate(&operands, &operators)
+            .filter(|_| operands.len() >= 2)
+            .ok_or_else(|| CrossmathError::InvalidEquation(expression(&operands, &operators)))?;
+        Ok(Self {
+            operands,
+            operators,
             result,
+        })
+    }
+
+    pub fn random(settings: &DifficultySettings, rng: &mut impl Rng) -> Option<Self> {
+        (0..100).find_map(|_| {
+            let x = rng.gen_range(settings.min_number..=settings.max_number);
+            Self::generate(MatchParameter::X.term(), x, settings, rng)
+        })
+    }
+
+    /// Builds an equation where the term at index `term` is `value`.
+    /// A random operator is tried first; when it can't keep every term a positive
+    /// integer around `value` (a prime can't be split by a product for instance),
+    /// we fall back on the other allowed operators.
+    pub fn generate(
+        term: usize,
+        value: u32,
+        settings: &DifficultySettings,
+        rng: &mut impl Rng,
+    ) -> Option<Self> {
+        if settings.operands > 2 {
+            return (0..100).find_map(|_| Self::try_generate_chain(term, value, settings, rng));
         }
+        let param = MatchParameter::from_term(term)?;
+        let operator = settings.random_operator(rng)?;
+        Self::try_generate(param, value, operator, settings, rng).or_else(|| {
+            settings
+                .allowed_operators()
+                .filter(|other| *other != operator)
+                .find_map(|other| Self::try_generate(param, value, other, settings, rng))
+        })
     }
 
-    pub fn generate(param: MatchParameter, value: u32) -> Self {
-        let mut x: u32;
-        let y: u32;
-        let result: u32;
-        let operator: Operation;
-
-        let mut rng = rand::thread_rng();
-        match param {
-            MatchParameter::X => {
-                x = value;
-                y = rng.gen_range(1..MAX_NUMBER);
-                operator = if x > y {
-                    get_random_operator()
-                } else {
-                    Operation::Plus
-                };
+    fn try_generate(
+        param: MatchParameter,
+        value: u32,
+        operator: Operation,
+        settings: &DifficultySettings,
+        rng: &mut impl Rng,
+    ) -> Option<Self> {
+        if value == 0 || value > settings.max_result {
+            return None;
+        }
+
+        let low = settings.min_number.max(1);
+        let high = settings.max_number;
+        let top = settings.max_result;
+
+        let (x, y) = match (param, operator) {
+            (MatchParameter::X, Operation::Plus) => {
+                (value, pick(rng, low, high.min(top.checked_sub(value)?))?)
             }
-            MatchParameter::Y => {
-                y = value;
-                x = rng.gen_range(1..MAX_NUMBER);
-                operator = get_random_operator();
-                if operator == Operation::Minus && x < y {
-                    x = rng.gen_range(y..2*MAX_NUMBER);
-                }
+            (MatchParameter::X, Operation::Minus) => (value, pick(rng, low, high.min(value - 1))?),
+            (MatchParameter::X, Operation::Times) => (value, pick(rng, low, high.min(top / value))?),
+            (MatchParameter::X, Operation::Divide) => {
+                (value, pick_divisor(rng, value, low, high)?)
             }
-            MatchParameter::Result => {
-                result = value;
-                x = rng.gen_range(1..MAX_NUMBER);
-                match x.cmp(&result) {
-                    Ordering::Greater => {
-                        y = x - result;
-                        operator = Operation::Minus;
-                    }
-                    _ => {
-                        y = result - x;
-                        operator = Operation::Plus;
-                    }
-                };
-                
-                return Self {
-                    x,
-                    y,
-                    operator,
-                    result,
-                };
+            (MatchParameter::Y, Operation::Plus) => {
+                (pick(rng, low, high.min(top.checked_sub(value)?))?, value)
             }
-        }
+            (MatchParameter::Y, Operation::Minus) => {
+                (pick(rng, value + 1, top.min(value.saturating_add(high)))?, value)
+            }
+            (MatchParameter::Y, Operation::Times) => (pick(rng, low, high.min(top / value))?, value),
+            (MatchParameter::Y, Operation::Divide) => (value * pick(rng, 2, top / value)?, value),
+            (MatchParameter::Result, Operation::Plus) => {
+                let x = pick(rng, low, high.min(value.checked_sub(low)?))?;
+                (x, value - x)
+            }
+            (MatchParameter::Result, Operation::Minus) => {
+                let y = pick(rng, low, high.min(top - value))?;
+                (value + y, y)
+            }
+            (MatchParameter::Result, Operation::Times) => {
+                let x = pick_divisor(rng, value, low, high)?;
+                (x, value / x)
+            }
+            (MatchParameter::Result, Operation::Divide) => {
+                let y = pick(rng, low.max(2), high.min(top / value))?;
+                (value * y, y)
+            }
+        };
 
-        let (result, x, y) = get_result(x, y, &operator);
+        Self::new(x, y, operator).ok()
+    }
 
-        Self {
-            x,
-            y,
-            operator,
-            result,
+    /// Builds an equation of more than two operands, with random operators and operands
+    /// around `value`. When the result is imposed, one of the operands is computed from
+    /// the others.
+    fn try_generate_chain(
+        term: usize,
+        value: u32,
+        settings: &DifficultySettings,
+        rng: &mut impl Rng,
+    ) -> Option<Self> {
+        let count = settings.operands as usize;
+        let top = settings.max_result;
+        if value == 0 || value > top {
+            return None;
         }
-    }
 
-    pub fn random_with(value: u32) -> Self {
-        let result: u32;
-        let mut x: u32;
-        let mut y: u32;
-        let operator: Operation;
-
-        let mut rng = rand::thread_rng();
-        let proba: f64 = rng.gen();
-        if proba < 0.33 {
-            result = value;
-            x = rng.gen_range(1..MAX_NUMBER);
-            match x.cmp(&result) {
-                Ordering::Greater => {
-                    y = x - result;
-                    operator = Operation::Minus;
+        let low = settings.min_number.max(1);
+        let high = settings.max_number;
+        let operators = (1..count)
+            .map(|_| settings.random_operator(rng))
+            .collect::<Option<Vec<_>>>()?;
+        let solved = (term == count).then(|| rng.gen_range(0..count as u32) as usize);
+
+        let mut operands: Vec<Option<u32>> = Vec::with_capacity(count);
+        // Value of the product being written, to keep its divisions exact
+        let mut product = None;
+        for position in 0..count {
+            let operator = position.checked_sub(1).map(|previous| operators[previous]);
+            let operand = if position == term {
+                Some(value)
+            } else if Some(position) == solved {
+                None
+            } else {
+                match (operator, product) {
+                    (Some(Operation::Divide), Some(product)) => Some(pick_divisor(rng, product, low, high)?),
+                    _ => Some(pick(rng, low, high)?),
                 }
-                _ => {
-                    y = result - x;
-                    operator = Operation::Plus;
+            };
+            product = match operator {
+                Some(operator @ (Operation::Times | Operation::Divide)) => {
+                    product.zip(operand).and_then(|(product, operand)| operator.apply(product, operand))
                 }
-            }
-        } else if proba < 0.66 {
-            x = value;
-            y = rng.gen_range(1..MAX_NUMBER);
-            operator = get_random_operator();
-            (result, x, y) = get_result(x, y, &operator);
-        } else {
-            y = value;
-            x = rng.gen_range(1..MAX_NUMBER);
-            operator = get_random_operator();
-            (result, x, y) = get_result(x, y, &operator);
+                _ => operand,
+            };
+            operands.push(operand);
         }
 
-        Self {
-            x,
-            y,
-            operator,
-            result,
+        if let Some(solved) = solved {
+            operands[solved] = Some(solve_operand(&operands, &operators, value)?);
         }
+        let eq = Self::chain(operands.into_iter().collect::<Option<_>>()?, operators).ok()?;
+        eq.terms().iter().all(|&term| term <= top).then_some(eq)
     }
 
-    pub fn get_random_number(&self) -> u32 {
-        let mut rng = rand::thread_rng();
-        let proba: f64 = rng.gen();
-        if proba < 0.33 {
-            self.result
-        } else if proba < 0.66 {
-            self.x
-        } else {
-            self.y
-        }
+    pub fn random_with(value: u32, settings: &DifficultySettings, rng: &mut impl Rng) -> Option<Self> {
+        let term = get_random_term(settings.operands as usize, rng);
+        Self::generate(term, value, settings, rng)
+    }
+
+    pub fn get_random_number(&self, rng: &mut impl Rng) -> u32 {
+        let terms = self.terms();
+        terms[rng.gen_range(0..terms.len() as u32) as usize]
     }
 
     pub fn get_x(&self) -> u32 {
-        self.x
+        self.operands[0]
     }
 
     pub fn get_y(&self) -> u32 {
-        self.y
+        self.operands[1]
     }
 
     pub fn get_result(&self) -> u32 {
         self.result
     }
 
-    pub fn to_array(&self, dir: Direction) -> Vec<String> {
-        let oper = match self.operator {
-            Operation::Plus => "+",
-            Operation::Minus => "-",
-        };
-        match (dir, oper) {
-            (Direction::Left, "-") | (Direction::Up, "-") => vec![
-                self.y.to_string(),
-                oper.to_string(),
-                self.x.to_string(),
-                "=".to_string(),
-                self.result.to_string(),
-            ],
-            _ => vec![
-                self.x.to_string(),
-                oper.to_string(),
-                self.y.to_string(),
-                "=".to_string(),
-                self.result.to_string(),
-            ],
+    pub fn operands(&self) -> &[u32] {
+        &self.operands
+    }
+
+    pub fn operators(&self) -> &[Operation] {
+        &self.operators
+    }
+
+    /** Operands then the result */
+    pub fn terms(&self) -> Vec<u32> {
+        let mut terms = self.operands.clone();
+        terms.push(self.result);
+        terms
+    }
+
+    /** Number of cells taken in the grid */
+    pub fn len(&self) -> usize {
+        2 * self.operands.len() + 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operands.is_empty()
+    }
+
+    /** Whether the operands can be written in any order */
+    pub fn is_commutative(&self) -> bool {
+        self.operators.iter().all(Operation::is_commutative)
+    }
+
+    /// Terms in the order they are written in the grid, starting from the start position.
+    /// When going left or up, a non commutative equation is stored backwards so that
+    /// it still reads "result = a op b ..." from left to right and top to bottom.
+    pub fn to_array(&self, dir: Direction) -> Vec<Cell> {
+        let mut cells = vec![Cell::number(self.operands[0])];
+        for (&operator, &operand) in self.operators.iter().zip(&self.operands[1..]) {
+            cells.push(Cell::Operator(operator));
+            cells.push(Cell::number(operand));
         }
+        if is_backwards(dir) && !self.is_commutative() {
+            cells.reverse();
+        }
+        cells.push(Cell::Equals);
+        cells.push(Cell::number(self.result));
+        cells
     }
 
     pub fn get_operation(&self) -> Operation {
-        self.operator
+        self.operators[0]
     }
 
-    pub fn to_string(&self) -> String {
-        let sign = match self.operator {
-            Operation::Minus => '-',
-            Operation::Plus => '+',
-        };
-        format!("{} {} {} = {}", self.x, sign, self.y, self.result)
+    pub fn get_start_position(&self, grid_width: i32, dir: Direction, term: usize, param_position: usize) -> usize {
+        let dir_step = get_direction_step(dir, grid_width);
+        (param_position as i32 - self.get_param_offset(dir, term) * dir_step) as usize
     }
 
-    pub fn get_start_position(&self, grid_width: i32, dir: Direction, param: MatchParameter, param_position: usize) -> usize {
-        let position = param_position;
-        let dir_step = get_direction_step(dir, grid_width);
-        let start_position = match param {
-            MatchParameter::Y => match (self.operator, dir) {
-                (Operation::Minus, Direction::Left) | (Operation::Minus, Direction::Up) => position as i32,
-                _ => (position as i32) - 2 * dir_step
-            },
-            MatchParameter::X => match (self.operator, dir) {
-                (Operation::Minus, Direction::Left) | (Operation::Minus, Direction::Up) => position as i32 - 2 * dir_step,
-                _ => position as i32
-            },
-            MatchParameter::Result => (position as i32) - 4 * dir_step,
-        } as usize;
-        start_position
+    /** Index of the matched term in `to_array(dir)` */
+    pub fn get_param_offset(&self, dir: Direction, term: usize) -> i32 {
+        let count = self.operands.len();
+        let position = match term {
+            _ if term >= count => count,
+            _ if is_backwards(dir) && !self.is_commutative() => count - 1 - term,
+            _ => term,
+        };
+        2 * position as i32
     }
 
     pub fn show(&self) {
-        println!("{}", self.to_string());
+        println!("{}", self);
+    }
+}
+
+impl fmt::Display for Equation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} = {}", expression(&self.operands, &self.operators), self.result)
+    }
+}
+
+/** "a op b op c ..." */
+fn expression(operands: &[u32], operators: &[Operation]) -> String {
+    let mut text = operands.first().map_or(String::new(), u32::to_string);
+    for (operator, operand) in operators.iter().zip(operands.iter().skip(1)) {
+        text.push_str(&format!(" {} {}", operator.symbol(), operand));
+    }
+    text
+}
+
+/** Equations going left or up are read from their last cell */
+pub fn is_backwards(dir: Direction) -> bool {
+    matches!(dir, Direction::Left | Direction::Up)
+}
+
+/// Operands grouped by products and divisions, each group with the sign it is
+/// added to the total with.
+fn groups(operators: &[Operation]) -> Vec<(Operation, Range<usize>)> {
+    let mut groups = vec![(Operation::Plus, 0..1)];
+    for (position, &operator) in operators.iter().enumerate() {
+        match operator {
+            Operation::Plus | Operation::Minus => groups.push((operator, position + 1..position + 2)),
+            Operation::Times | Operation::Divide => {
+                if let Some((_, group)) = groups.last_mut() {
+                    group.end += 1;
+                }
+            }
+        }
+    }
+    groups
+}
+
+/// Value of "a op b op c ...", products and divisions being computed before additions
+/// and subtractions, from left to right. Every step has to be a positive integer.
+pub fn evaluate(operands: &[u32], operators: &[Operation]) -> Option<u32> {
+    if operands.len() != operators.len() + 1 {
+        return None;
     }
+    groups(operators).into_iter().try_fold(0, |total, (sign, group)| {
+        let product = (group.start + 1..group.end)
+            .try_fold(operands[group.start], |product, position| {
+                operators[position - 1].apply(product, operands[position])
+            })?;
+        sign.apply(total, product)
+    })
 }
 
-fn get_random_operator() -> Operation {
-    if rand::thread_rng().gen_range(0..100) > 50 {
-        Operation::Plus
+/// The missing operand of "a op b op c ... = result", when there is a positive integer
+/// making the equation hold.
+pub fn solve_operand(operands: &[Option<u32>], operators: &[Operation], result: u32) -> Option<u32> {
+    let missing = operands.iter().position(Option::is_none)?;
+    let divides = |position: usize| position > 0 && operators[position - 1] == Operation::Divide;
+
+    // Every other group is known, which gives the value of the missing operand's one
+    let mut others = 0i64;
+    let mut unknown = None;
+    for (sign, group) in groups(operators) {
+        if group.contains(&missing) {
+            unknown = Some((sign, group));
+            continue;
+        }
+        let product = (group.start + 1..group.end).try_fold(operands[group.start]?, |product, position| {
+            operators[position - 1].apply(product, operands[position]?)
+        })?;
+        others = match sign {
+            Operation::Minus => others - product as i64,
+            _ => others + product as i64,
+        };
+    }
+    let (sign, group) = unknown?;
+    let needed = match sign {
+        Operation::Minus => others - result as i64,
+        _ => result as i64 - others,
+    };
+    let needed = u64::try_from(needed).ok().filter(|&needed| needed > 0)?;
+
+    // The group reads "a × b ÷ c ...", the missing operand multiplies or divides the others
+    let (mut numerator, mut denominator) = (1u64, 1u64);
+    for position in group.filter(|&position| position != missing) {
+        let operand = operands[position]? as u64;
+        if divides(position) {
+            denominator = denominator.checked_mul(operand)?;
+        } else {
+            numerator = numerator.checked_mul(operand)?;
+        }
+    }
+    let (dividend, divisor) = if divides(missing) {
+        (numerator, needed.checked_mul(denominator)?)
     } else {
-        Operation::Minus
+        (needed.checked_mul(denominator)?, numerator)
+    };
+    if divisor == 0 || !dividend.is_multiple_of(divisor) {
+        return None;
     }
+    let value = u32::try_from(dividend / divisor).ok()?;
+
+    let filled: Vec<u32> = operands.iter().map(|operand| operand.unwrap_or(value)).collect();
+    (evaluate(&filled, operators) == Some(result)).then_some(value)
 }
 
-/** Returns (result, x, y) */
-fn get_result(x: u32, y: u32, operator: &Operation) -> (u32, u32, u32) {
-    match operator {
-        Operation::Plus => (x + y, x, y),
-        Operation::Minus => match x.cmp(&y) {
-            Ordering::Less => (y - x, y, x),
-            _ => (x - y, x, y),
-        },
+/** A random number between low and high included, if there is any */
+fn pick(rng: &mut impl Rng, low: u32, high: u32) -> Option<u32> {
+    if low > high {
+        return None;
     }
+    Some(rng.gen_range(low..=high))
+}
+
+/** A random divisor of value between low and high, other than 1 and value itself */
+fn pick_divisor(rng: &mut impl Rng, value: u32, low: u32, high: u32) -> Option<u32> {
+    // Divisors come in pairs around the square root of value
+    let mut divisors: Vec<u32> = (2..)
+        .take_while(|&d: &u32| d.saturating_mul(d) <= value)
+        .filter(|d| value.is_multiple_of(*d))
+        .flat_map(|d| [d, value / d])
+        .filter(|&d| d >= low && d <= high && d < value)
+        .collect();
+    divisors.sort_unstable();
+    divisors.dedup();
+    divisors.choose(rng).copied()
+}
+
+/// Returns (result, x, y), the operands of a subtraction or a division being swapped
+/// when only the other order gives a whole positive result.
+fn get_result(x: u32, y: u32, operator: &Operation) -> Result<(u32, u32, u32), CrossmathError> {
+    let (x, y) = match operator {
+        Operation::Minus if x < y => (y, x),
+        Operation::Divide if y == 0 || !x.is_multiple_of(y) => match x != 0 && y.is_multiple_of(x) {
+            true => (y, x),
+            false => return Err(CrossmathError::InexactDivision { x, y }),
+        },
+        _ => (x, y),
+    };
+    let result = operator
+        .apply(x, y)
+        .ok_or_else(|| CrossmathError::InvalidEquation(expression(&[x, y], &[*operator])))?;
+    Ok((result, x, y))
 }
 
 pub fn get_direction_step(dir: Direction, width: i32) -> i32 {
@@ -270,30 +518,173 @@ mod tests {
 
     #[test]
     fn check_formatting() {
-        let eq = Equation::new(2, 3, Operation::Plus);
+        let eq = Equation::new(2, 3, Operation::Plus).unwrap();
         assert_eq!(eq.to_string(), "2 + 3 = 5", "Addition has failed");
 
-        let eq2 = Equation::new(5, 3, Operation::Minus);
+        let eq2 = Equation::new(5, 3, Operation::Minus).unwrap();
         assert_eq!(eq2.to_string(), "5 - 3 = 2", "Substraction has failed");
+
+        let eq3 = Equation::new(4, 3, Operation::Times).unwrap();
+        assert_eq!(eq3.to_string(), "4 × 3 = 12", "Multiplication has failed");
+
+        let eq4 = Equation::new(3, 12, Operation::Divide).unwrap();
+        assert_eq!(eq4.to_string(), "12 ÷ 3 = 4", "Division has failed");
+
+        assert_eq!(
+            Equation::new(7, 2, Operation::Divide).err(),
+            Some(CrossmathError::InexactDivision { x: 7, y: 2 })
+        );
+        for (x, y, operator, text) in [
+            (3, 3, Operation::Minus, "3 - 3"),
+            (0, 5, Operation::Divide, "0 ÷ 5"),
+            (0, 5, Operation::Times, "0 × 5"),
+            (u32::MAX, 1, Operation::Plus, "4294967295 + 1"),
+            (70_000, 70_000, Operation::Times, "70000 × 70000"),
+        ] {
+            assert_eq!(
+                Equation::new(x, y, operator).err(),
+                Some(CrossmathError::InvalidEquation(text.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn operations_can_be_reversed() {
+        let operations = [Operation::Plus, Operation::Minus, Operation::Times, Operation::Divide];
+        for operation in operations {
+            let eq = Equation::new(12, 3, operation).unwrap();
+            let (x, y, result) = (eq.get_x(), eq.get_y(), eq.get_result());
+            assert_eq!(operation.apply(x, y), Some(result));
+            assert_eq!(operation.solve_x(y, result), Some(x), "{}", eq);
+            assert_eq!(operation.solve_y(x, result), Some(y), "{}", eq);
+        }
+        assert_eq!(Operation::Minus.apply(3, 3), None);
+        assert_eq!(Operation::Divide.solve_y(7, 2), None);
+        assert_eq!(Operation::Times.solve_x(5, 12), None);
+    }
+
+    #[test]
+    fn generated_terms_are_positive_integers() {
+        let mut rng = PuzzleRng::seed_from_u64(7);
+        let params = [MatchParameter::X, MatchParameter::Y, MatchParameter::Result];
+        let difficulties = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert];
+        for settings in difficulties.map(|difficulty| difficulty.settings()) {
+            for value in 1..=settings.max_result {
+                for param in params {
+                    check_generated(param, value, &settings, &mut rng);
+                }
+            }
+        }
+    }
+
+    fn check_generated(param: MatchParameter, value: u32, settings: &DifficultySettings, rng: &mut PuzzleRng) {
+        let Some(eq) = Equation::generate(param.term(), value, settings, rng) else {
+            return;
+        };
+        let (x, y, result) = (eq.get_x(), eq.get_y(), eq.get_result());
+        assert!(x > 0 && y > 0 && result > 0, "{} has a null term", eq);
+        assert!(x.max(y).max(result) <= settings.max_result, "{} is too big", eq);
+        assert!(settings.allowed_operators().any(|op| op == eq.get_operation()));
+        let expected = match eq.get_operation() {
+            Operation::Plus => x + y,
+            Operation::Minus => x - y,
+            Operation::Times => x * y,
+            Operation::Divide => {
+                assert_eq!(x % y, 0, "{} is not an exact division", eq);
+                x / y
+            }
+        };
+        assert_eq!(result, expected, "{} is wrong", eq);
+        let anchored = match param {
+            MatchParameter::X => x,
+            MatchParameter::Y => y,
+            MatchParameter::Result => result,
+        };
+        assert_eq!(anchored, value, "{} is not anchored on {}", eq, value);
     }
 
     #[test]
     fn check_random_with() {
         let random_match = 2;
-        let eq = Equation::random_with(random_match);
+        let mut rng = PuzzleRng::seed_from_u64(2);
+        let settings = DifficultySettings::default();
+        let eq = Equation::random_with(random_match, &settings, &mut rng).unwrap();
         assert!(eq.to_string().contains('2'));
     }
 
+    #[test]
+    fn longer_equations_follow_precedence() {
+        use Operation::*;
+        assert_eq!(evaluate(&[2, 3, 4], &[Plus, Times]), Some(14));
+        assert_eq!(evaluate(&[2, 3, 4], &[Times, Plus]), Some(10));
+        assert_eq!(evaluate(&[20, 4, 3, 2], &[Minus, Times, Divide]), Some(14));
+        assert_eq!(evaluate(&[5, 5, 1], &[Minus, Plus]), None);
+        assert_eq!(evaluate(&[7, 2, 3], &[Divide, Times]), None);
+
+        let operators = [Minus, Times, Divide];
+        for (missing, expected) in [20, 4, 3, 2].into_iter().enumerate() {
+            let mut operands = vec![Some(20), Some(4), Some(3), Some(2)];
+            operands[missing] = None;
+            assert_eq!(solve_operand(&operands, &operators, 14), Some(expected));
+        }
+        assert_eq!(solve_operand(&[Some(20), None, Some(3), Some(2)], &operators, 15), None);
+
+        let eq = Equation::chain(vec![12, 3, 5], vec![Divide, Plus]).unwrap();
+        assert_eq!(eq.to_string(), "12 ÷ 3 + 5 = 9");
+        assert_eq!(eq.len(), 7);
+        assert_eq!(
+            Equation::chain(vec![3, 5], vec![Minus]).err(),
+            Some(CrossmathError::InvalidEquation("3 - 5".to_string()))
+        );
+    }
+
+    #[test]
+    fn longer_equations_anchor_any_term() {
+        let mut rng = PuzzleRng::seed_from_u64(3);
+        for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Expert] {
+            let settings = DifficultySettings { operands: 3, ..difficulty.settings() };
+            for term in 0..=3 {
+                for value in [6, 12, 17, 30] {
+                    let Some(eq) = Equation::generate(term, value, &settings, &mut rng) else {
+                        continue;
+                    };
+                    assert_eq!(eq.terms()[term], value, "{}", eq);
+                    assert_eq!(eq.operands().len(), 3);
+                    assert_eq!(evaluate(eq.operands(), eq.operators()), Some(eq.get_result()));
+                    assert!(eq.terms().iter().all(|&term| term > 0 && term <= settings.max_result), "{}", eq);
+                }
+            }
+            assert!(Equation::generate(3, 12, &settings, &mut rng).is_some());
+        }
+    }
+
+    fn symbols(cells: Vec<Cell>) -> Vec<String> {
+        cells.iter().map(|cell| cell.to_string()).collect()
+    }
+
     #[test]
     fn test_representation() {
-        let eq = Equation::new(3, 4, Operation::Plus);
-        assert_eq!(eq.to_array(Direction::Right), ["3", "+", "4", "=", "7"]);
+        let eq = Equation::new(3, 4, Operation::Plus).unwrap();
+        assert_eq!(symbols(eq.to_array(Direction::Right)), ["3", "+", "4", "=", "7"]);
 
-        let eq = Equation::new(4, 3, Operation::Minus);
-        assert_eq!(eq.to_array(Direction::Right), ["4", "-", "3", "=", "1"]);
+        let eq = Equation::new(4, 3, Operation::Minus).unwrap();
+        assert_eq!(symbols(eq.to_array(Direction::Right)), ["4", "-", "3", "=", "1"]);
 
-        let eq = Equation::new(4, 3, Operation::Minus);
-        assert_eq!(eq.to_array(Direction::Left), ["3", "-", "4", "=", "1"]);
+        let eq = Equation::new(4, 3, Operation::Minus).unwrap();
+        assert_eq!(symbols(eq.to_array(Direction::Left)), ["3", "-", "4", "=", "1"]);
+
+        let eq = Equation::new(4, 3, Operation::Times).unwrap();
+        assert_eq!(symbols(eq.to_array(Direction::Up)), ["4", "×", "3", "=", "12"]);
+
+        let eq = Equation::new(12, 3, Operation::Divide).unwrap();
+        assert_eq!(symbols(eq.to_array(Direction::Up)), ["3", "÷", "12", "=", "4"]);
+
+        let eq = Equation::chain(vec![2, 3, 4], vec![Operation::Times, Operation::Minus]).unwrap();
+        assert_eq!(symbols(eq.to_array(Direction::Right)), ["2", "×", "3", "-", "4", "=", "2"]);
+        assert_eq!(symbols(eq.to_array(Direction::Left)), ["4", "-", "3", "×", "2", "=", "2"]);
+        assert_eq!(eq.get_param_offset(Direction::Left, 0), 4);
+        assert_eq!(eq.get_param_offset(Direction::Right, 2), 4);
+        assert_eq!(eq.get_param_offset(Direction::Up, 3), 6);
     }
 
     #[test]
@@ -301,22 +692,22 @@ mod tests {
         let directions = [Direction::Left, Direction::Right, Direction::Down, Direction::Up];
         let width = 100;
         let param_position = 50;
-        let eq = Equation::new(3, 4, Operation::Plus);
+        let eq = Equation::new(3, 4, Operation::Plus).unwrap();
 
-        let param = MatchParameter::X;
+        let param = MatchParameter::X.term();
         for dir in directions {
             let pos = eq.get_start_position(width, dir, param, param_position);
             assert_eq!(param_position, pos);
         }
 
-        let param = MatchParameter::Y;
+        let param = MatchParameter::Y.term();
         for dir in directions {
             let delta = get_direction_step(dir, width);
             let pos = eq.get_start_position(width, dir, param, param_position);
             assert_eq!(50 - 2*delta, pos as i32);
         }
 
-        let param = MatchParameter::Result;
+        let param = MatchParameter::Result.term();
         for dir in directions {
             let delta = get_direction_step(dir, width);
             let pos = eq.get_start_position(width, dir, param, param_position);
@@ -325,13 +716,18 @@ mod tests {
     }
 
     #[test]
-    fn test_get_start_position_for_substraction() {
-        let directions = [Direction::Left, Direction::Right, Direction::Down, Direction::Up];
+    fn test_get_start_position_for_substraction_and_division() {
         let width = 100;
         let param_position = 50;
-        let eq = Equation::new(4, 3, Operation::Minus);
+        for eq in [Equation::new(4, 3, Operation::Minus).unwrap(), Equation::new(12, 3, Operation::Divide).unwrap()] {
+            check_start_positions_backwards(&eq, width, param_position);
+        }
+    }
+
+    fn check_start_positions_backwards(eq: &Equation, width: i32, param_position: usize) {
+        let directions = [Direction::Left, Direction::Right, Direction::Down, Direction::Up];
 
-        let param = MatchParameter::X;
+        let param = MatchParameter::X.term();
         for dir in [Direction::Right, Direction::Down] {
             let pos = eq.get_start_position(width, dir, param, param_position);
             assert_eq!(param_position, pos);
@@ -343,7 +739,7 @@ mod tests {
             assert_eq!(50 - 2*delta, pos as i32);
         }
 
-        let param = MatchParameter::Y;
+        let param = MatchParameter::Y.term();
         for dir in [Direction::Right, Direction::Down] {
             let delta = get_direction_step(dir, width);
             let pos = eq.get_start_position(width, dir, param, param_position);
@@ -355,7 +751,7 @@ mod tests {
             assert_eq!(param_position, pos);
         }
 
-        let param = MatchParameter::Result;
+        let param = MatchParameter::Result.term();
         for dir in directions {
             let delta = get_direction_step(dir, width);
             let pos = eq.get_start_position(width, dir, param, param_position);
@@ -0,0 +1,93 @@
+use crate::prelude::*;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum CrossmathError {
+    InvalidConfig(ConfigError),
+    /** The settings don't allow any equation to be built */
+    NoEquationPossible,
+    InexactDivision { x: u32, y: u32 },
+    /** The operands of an equation don't give a positive integer, with its expression */
+    InvalidEquation(String),
+    OutOfBounds { start: usize, direction: Direction, width: u32 },
+    NotANumber { position: usize },
+    /** Generation ran out of attempts, `partial` is the biggest grid that was built */
+    CouldNotPlace { requested: u32, placed: u32, partial: Grid },
+    /** The answer doesn't have one value per cell of the grid */
+    AnswerLength { expected: usize, found: usize },
+    /** A hidden cell of the answer holds something else than a number */
+    InvalidAnswer { position: usize, value: String },
+    /** A saved puzzle couldn't be read or written */
+    Json(String),
+    UnsupportedVersion { found: u32, supported: u32 },
+    ShareCode(ShareCodeError),
+    /** Only the cells hidden to the player can be filled */
+    NotAHiddenCell { position: usize },
+}
+
+impl fmt::Display for CrossmathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrossmathError::InvalidConfig(error) => write!(f, "Invalid configuration : {}", error),
+            CrossmathError::NoEquationPossible => {
+                write!(f, "No equation can be built with these settings.")
+            }
+            CrossmathError::InexactDivision { x, y } => {
+                write!(f, "Can't divide {} by {} without a remainder.", x, y)
+            }
+            CrossmathError::InvalidEquation(expression) => {
+                write!(f, "{} doesn't give a positive integer at every step.", expression)
+            }
+            CrossmathError::OutOfBounds { start, direction, width } => write!(
+                f,
+                "Index out of bounds : dir={:?} start={} size={}",
+                direction, start, width
+            ),
+            CrossmathError::NotANumber { position } => {
+                write!(f, "Grid creation : the cell {} should hold a number.", position)
+            }
+            CrossmathError::CouldNotPlace { requested, placed, .. } => write!(
+                f,
+                "Could not place {} equations, only {} fit in the grid.",
+                requested, placed
+            ),
+            CrossmathError::AnswerLength { expected, found } => write!(
+                f,
+                "The answer should have {} cells, got {}.",
+                expected, found
+            ),
+            CrossmathError::InvalidAnswer { position, value } => {
+                write!(f, "The answer for the cell {} should be a number, got '{}'.", position, value)
+            }
+            CrossmathError::Json(error) => write!(f, "Invalid saved puzzle : {}", error),
+            CrossmathError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "The puzzle was saved with version {} of the format, only versions up to {} can be read.",
+                found, supported
+            ),
+            CrossmathError::ShareCode(error) => write!(f, "Invalid share code : {}", error),
+            CrossmathError::NotAHiddenCell { position } => {
+                write!(f, "The cell {} is not one the player has to fill.", position)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CrossmathError {}
+
+impl From<ConfigError> for CrossmathError {
+    fn from(error: ConfigError) -> Self {
+        CrossmathError::InvalidConfig(error)
+    }
+}
+
+impl From<ShareCodeError> for CrossmathError {
+    fn from(error: ShareCodeError) -> Self {
+        CrossmathError::ShareCode(error)
+    }
+}
+
+impl From<CrossmathError> for JsValue {
+    fn from(error: CrossmathError) -> Self {
+        JsError::new(&error.to_string()).into()
+    }
+}
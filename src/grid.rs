@@ -0,0 +1,253 @@
+use std::ops::{Index, IndexMut};
+
+use crate::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Cell {
+    Empty,
+    Number { value: u32, hidden: bool },
+    Operator(Operation),
+    Equals,
+}
+
+impl Cell {
+    pub fn number(value: u32) -> Self {
+        Cell::Number {
+            value,
+            hidden: false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Cell::Empty
+    }
+
+    pub fn value(&self) -> Option<u32> {
+        match self {
+            Cell::Number { value, .. } => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        matches!(self, Cell::Number { hidden: true, .. })
+    }
+
+    /** The same cell, with its value shown to the player */
+    pub fn revealed(&self) -> Self {
+        match self {
+            Cell::Number { value, .. } => Cell::number(*value),
+            _ => *self,
+        }
+    }
+}
+
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cell::Empty => write!(f, " "),
+            Cell::Number { hidden: true, .. } => write!(f, "?"),
+            Cell::Number { value, .. } => write!(f, "{}", value),
+            Cell::Operator(operation) => write!(f, "{}", operation.symbol()),
+            Cell::Equals => write!(f, "="),
+        }
+    }
+}
+
+/// A rectangle of cells stored row by row.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Grid {
+    width: u32,
+    height: u32,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::Empty; width as usize * height as usize],
+        }
+    }
+
+    /** A grid holding the given cells row by row, if there is one cell per position */
+    pub fn from_cells(width: u32, height: u32, cells: Vec<Cell>) -> Option<Self> {
+        if (width as usize).checked_mul(height as usize) != Some(cells.len()) {
+            return None;
+        }
+        Some(Self { width, height, cells })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn cells(&self) -> &[Cell] {
+        &self.cells
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Cell> {
+        self.cells.iter()
+    }
+
+    pub fn get_idx(&self, x: u32, y: u32) -> usize {
+        (x + (y * self.width)) as usize
+    }
+
+    /** Returns (x, y) */
+    pub fn coordinates(&self, idx: usize) -> (u32, u32) {
+        let idx = idx as u32;
+        (idx % self.width, idx / self.width)
+    }
+
+    /// Index of the cell `steps` cells away from `idx` in the given direction,
+    /// or `None` when it falls outside of the grid (rows never wrap).
+    pub fn step(&self, idx: usize, dir: Direction, steps: i32) -> Option<usize> {
+        let (x, y) = self.coordinates(idx);
+        let (dx, dy) = match dir {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        };
+        let x = x as i64 + dx * steps as i64;
+        let y = y as i64 + dy * steps as i64;
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return None;
+        }
+        Some(self.get_idx(x as u32, y as u32))
+    }
+
+    /// The `len` cells met when walking in `dir`, starting `from` steps away from `anchor`.
+    pub fn line(&self, anchor: usize, dir: Direction, from: i32, len: usize) -> Option<Vec<usize>> {
+        (0..len as i32)
+            .map(|i| self.step(anchor, dir, from + i))
+            .collect()
+    }
+
+    /// Shrinks the grid to the smallest rectangle holding every non empty cell,
+    /// and returns how to find the new index of a cell from its former one.
+    pub fn crop(&mut self) -> impl Fn(usize) -> usize {
+        let mut xmin = self.width;
+        let mut xmax = 0;
+        let mut ymin = self.height;
+        let mut ymax = 0;
+
+        for (position, cell) in self.cells.iter().enumerate() {
+            if cell.is_empty() {
+                continue;
+            }
+            let (x, y) = self.coordinates(position);
+            xmin = xmin.min(x);
+            xmax = xmax.max(x);
+            ymin = ymin.min(y);
+            ymax = ymax.max(y);
+        }
+
+        let mut grid = if xmin > xmax || ymin > ymax {
+            (xmin, ymin) = (0, 0);
+            Grid::new(0, 0)
+        } else {
+            Grid::new((xmax - xmin) + 1, (ymax - ymin) + 1)
+        };
+        for new_idx in 0..grid.len() {
+            let (x, y) = grid.coordinates(new_idx);
+            grid[new_idx] = self[self.get_idx(x + xmin, y + ymin)];
+        }
+
+        let (old_width, new_width) = (self.width, grid.width);
+        *self = grid;
+
+        move |idx| {
+            let (x, y) = (idx as u32 % old_width, idx as u32 / old_width);
+            ((x - xmin) + (y - ymin) * new_width) as usize
+        }
+    }
+}
+
+impl Index<usize> for Grid {
+    type Output = Cell;
+
+    fn index(&self, idx: usize) -> &Cell {
+        &self.cells[idx]
+    }
+}
+
+impl IndexMut<usize> for Grid {
+    fn index_mut(&mut self, idx: usize) -> &mut Cell {
+        &mut self.cells[idx]
+    }
+}
+
+#[cfg(test)]
+impl Grid {
+    /// A grid holding the given equations written left to right from their start,
+    /// with the given cells hidden.
+    pub(crate) fn sample(
+        width: u32,
+        height: u32,
+        equations: Vec<(usize, Equation)>,
+        hidden: &[usize],
+    ) -> (Grid, Vec<PlacedEquation>) {
+        let mut grid = Grid::new(width, height);
+        let placed = equations
+            .into_iter()
+            .map(|(start, eq)| {
+                let cells = insert_equation(&mut grid, start, Direction::Right, eq, &mut vec![]).unwrap();
+                PlacedEquation::new(start, Direction::Right, cells)
+            })
+            .collect();
+        hidden.iter().for_each(|&idx| grid.hide(idx));
+        (grid, placed)
+    }
+
+    pub(crate) fn hide(&mut self, idx: usize) {
+        let value = self[idx].value().unwrap();
+        self[idx] = Cell::Number { value, hidden: true };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_do_not_wrap() {
+        let grid = Grid::new(5, 4);
+        let idx = grid.get_idx(4, 1);
+        assert_eq!(grid.step(idx, Direction::Right, 1), None);
+        assert_eq!(grid.step(idx, Direction::Left, 4), Some(grid.get_idx(0, 1)));
+        assert_eq!(grid.step(idx, Direction::Up, 2), None);
+        assert_eq!(grid.step(idx, Direction::Down, 2), Some(grid.get_idx(4, 3)));
+        assert_eq!(grid.line(idx, Direction::Left, -1, 3), None);
+    }
+
+    #[test]
+    fn crop_keeps_filled_cells() {
+        let mut grid = Grid::new(6, 6);
+        let first = grid.get_idx(2, 1);
+        let last = grid.get_idx(3, 4);
+        grid[first] = Cell::number(4);
+        grid[last] = Cell::Equals;
+        let moved = grid.crop();
+        assert_eq!(moved(last), grid.get_idx(1, 3));
+
+        assert_eq!((grid.width(), grid.height()), (2, 4));
+        assert_eq!(grid[0], Cell::number(4));
+        assert_eq!(grid[grid.get_idx(1, 3)], Cell::Equals);
+        assert_eq!(grid.iter().filter(|cell| cell.is_empty()).count(), 6);
+    }
+}
@@ -7,8 +7,7 @@ use crate::prelude::*;
 //     }
 // }
 
-pub fn get_random_match_parameter() -> MatchParameter {
-    let mut rng = rand::thread_rng();
+pub fn get_random_match_parameter(rng: &mut impl Rng) -> MatchParameter {
     let proba: f64 = rng.gen();
     if proba < 0.33 {
         return MatchParameter::Result;
@@ -18,14 +17,22 @@ pub fn get_random_match_parameter() -> MatchParameter {
     MatchParameter::X
 }
 
+/// A random term of an equation with the given number of operands, the result
+/// being the last one.
+pub fn get_random_term(operands: usize, rng: &mut impl Rng) -> usize {
+    if operands <= 2 {
+        return get_random_match_parameter(rng).term();
+    }
+    rng.gen_range(0..=operands as u32) as usize
+}
+
 // On va faire simple dans un premier temps :
 // - On ne regarde pas s'il est possible de faire une liaison ici avec le résultat
 pub fn is_insertion_possible(
-    width: i32,
-    grid: &Vec<String>,
+    grid: &Grid,
     position: usize,
     former_dir: Direction,
-    match_param: MatchParameter,
+    term: usize,
     eq: &Equation
 ) -> Option<Direction> {
 
@@ -39,68 +46,45 @@ pub fn is_insertion_possible(
     // log!("Trying : {}", eq.to_string());
 
     for dir in directions {
-        let delta = get_direction_step(dir, width);
-        let origin = eq.get_start_position(width, dir, match_param, match_position);
-        for i in 0..=4 {
-            let position = (origin as i32) + i * delta;
-            // log!("Cell {position} ({i})");
-
-            if position < 0 {
-                break;
-            }
-
-            let position = position as usize;
-            if position > grid.len() {
-                break;
-            }
-
-            // log!("{}", grid[position]);
-
-            if grid[position] != " " && position != match_position {
-                return None;
-            }
-            
-            if i == 4 {
-                return Some(dir);
-            }
+        let offset = eq.get_param_offset(dir, term);
+        let Some(positions) = grid.line(match_position, dir, -offset, eq.len()) else {
+            continue;
+        };
+
+        let is_free = positions
+            .iter()
+            .all(|&position| grid[position].is_empty() || position == match_position);
+        if is_free {
+            return Some(dir);
         }
     }
     None
 }
 
 pub fn insert_equation(
-    width: i32,
-    grid: &mut Vec<String>,
+    grid: &mut Grid,
     start_position: usize,
     dir: Direction,
     eq: Equation,
     marked: &mut Vec<(usize, Direction)>,
-) {
-    let delta = get_direction_step(dir, width);
-
+) -> Result<Vec<usize>, CrossmathError> {
     // log!("{}", eq.to_string());
 
     let representation = eq.to_array(dir);
-    for (idx, term) in representation.iter().enumerate() {
-        let position = (start_position as i32 + (idx as i32 * delta)) as usize;
-        if position > grid.len() {
-            let direction = match dir {
-                Direction::Down => "Down",
-                Direction::Up => "Up",
-                Direction::Left => "Left",
-                Direction::Right => "Right",
-            };
-            panic!(
-                "Index out of bounds : dir={} delta={} start={} size={}",
-                direction, delta, start_position, width
-            );
+    let positions = grid
+        .line(start_position, dir, 0, representation.len())
+        .ok_or(CrossmathError::OutOfBounds {
+            start: start_position,
+            direction: dir,
+            width: grid.width(),
+        })?;
+    for (&position, term) in positions.iter().zip(representation) {
+        grid[position] = term;
+        if term.value().is_some() {
+            marked.push((position, dir));
         }
-        grid[position] = term.to_string();
-        match term.trim().parse::<u32>() {
-            Ok(_) => marked.push((position, dir)),
-            Err(_) => {}
-        };
     }
+    Ok(positions)
 }
 
 #[cfg(test)]
@@ -109,11 +93,29 @@ mod tests {
 
     #[test]
     fn test_is_insertion_possible() {
-        let eq = Equation::new(3, 4, Operation::Plus);
-        let grid: Vec<String> = (0..100).map(|_| " ".to_string()).collect();
-        match is_insertion_possible(100, &grid, 50, Direction::Up, MatchParameter::X, &eq) {
-            Some(_) => {}
-            None => assert!(false, "The insertion is not working"),
-        }
+        let eq = Equation::new(3, 4, Operation::Plus).unwrap();
+        let grid = Grid::new(10, 10);
+        assert!(
+            is_insertion_possible(&grid, 50, Direction::Up, MatchParameter::X.term(), &eq).is_some(),
+            "The insertion is not working"
+        );
+    }
+
+    #[test]
+    fn test_insertion_does_not_wrap() {
+        let eq = Equation::new(3, 4, Operation::Plus).unwrap();
+        let mut grid = Grid::new(10, 10);
+        let position = grid.get_idx(8, 5);
+        let dir = is_insertion_possible(&grid, position, Direction::Up, MatchParameter::X.term(), &eq);
+        assert_eq!(dir, Some(Direction::Left));
+
+        let mut marked = vec![];
+        insert_equation(&mut grid, position, Direction::Left, eq, &mut marked).unwrap();
+        assert_eq!(grid[grid.get_idx(4, 5)], Cell::number(7));
+        assert_eq!(marked.len(), 3);
+
+        let eq = Equation::new(3, 4, Operation::Plus).unwrap();
+        let error = insert_equation(&mut grid, position, Direction::Right, eq, &mut marked);
+        assert!(matches!(error, Err(CrossmathError::OutOfBounds { .. })));
     }
 }
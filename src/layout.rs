@@ -0,0 +1,225 @@
+use crate::prelude::*;
+
+/// Failed attempts at linking a new equation to a layout before giving up on it.
+const ATTEMPTS_BEFORE_BACKTRACKING: u32 = 30;
+
+/// Where an equation has been written in the grid.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PlacedEquation {
+    pub start: usize,
+    pub direction: Direction,
+    /** Every cell of the equation, from the start position */
+    pub cells: Vec<usize>,
+    /// The equation this one was linked to when it was placed, and which of its
+    /// terms was shared, by index in `numbers`. The first equation of a grid isn't
+    /// linked to any other.
+    #[serde(deserialize_with = "read_link")]
+    pub link: Option<(usize, usize)>,
+    /** Equations sharing a cell with this one, by index in the equation list */
+    pub crossings: Vec<usize>,
+}
+
+/// A shared term, saved by name before equations could have more than two operands.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedTerm {
+    Index(usize),
+    Parameter(MatchParameter),
+}
+
+fn read_link<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<(usize, usize)>, D::Error> {
+    let link: Option<(usize, SavedTerm)> = Deserialize::deserialize(deserializer)?;
+    Ok(link.map(|(owner, term)| match term {
+        SavedTerm::Index(term) => (owner, term),
+        SavedTerm::Parameter(param) => (owner, param.term()),
+    }))
+}
+
+impl PlacedEquation {
+    /** An equation linked to no other one */
+    pub fn new(start: usize, direction: Direction, cells: Vec<usize>) -> Self {
+        Self {
+            start,
+            direction,
+            cells,
+            link: None,
+            crossings: vec![],
+        }
+    }
+
+    /** Numbers on the left of the equals sign */
+    pub fn operand_count(&self) -> usize {
+        self.cells.len() / 2
+    }
+
+    /// Cells of the operands in the order they are computed, then of the result.
+    /// Equations going left or up read backwards, from the cell before the equals sign.
+    pub fn numbers(&self) -> Vec<usize> {
+        let mut numbers = self.in_reading_order(0);
+        numbers.push(self.result_cell());
+        numbers
+    }
+
+    /** Cells of the operators, in the order they are computed */
+    pub fn operator_cells(&self) -> Vec<usize> {
+        self.in_reading_order(1)
+    }
+
+    pub fn result_cell(&self) -> usize {
+        self.cells[self.cells.len() - 1]
+    }
+
+    /** Every other cell on the left of the equals sign, from `first` */
+    fn in_reading_order(&self, first: usize) -> Vec<usize> {
+        let mut cells: Vec<usize> = self.cells[..self.cells.len() - 2].iter().skip(first).step_by(2).copied().collect();
+        if is_backwards(self.direction) {
+            cells.reverse();
+        }
+        cells
+    }
+
+    /** Cell of the term at the given index in `numbers` */
+    pub fn term(&self, term: usize) -> usize {
+        self.numbers()[term]
+    }
+
+    /** Operators read from the grid, `None` if a cell doesn't hold one */
+    pub fn operators(&self, grid: &Grid) -> Option<Vec<Operation>> {
+        self.operator_cells()
+            .into_iter()
+            .map(|cell| match grid[cell] {
+                Cell::Operator(operator) => Some(operator),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether "a op b ... = result" holds with the given value for each cell of the grid,
+    /// or `None` while one of its terms is unknown.
+    pub fn holds(&self, grid: &Grid, values: &[Option<u32>]) -> Option<bool> {
+        let Some(operators) = self.operators(grid) else {
+            return Some(false);
+        };
+        let numbers = self.numbers();
+        let (&result, operands) = numbers.split_last()?;
+        let operands = operands.iter().map(|&cell| values[cell]).collect::<Option<Vec<u32>>>()?;
+        Some(evaluate(&operands, &operators) == Some(values[result]?))
+    }
+
+    /** Follows the cells of the equation when the grid is reorganised */
+    pub fn reindex(&mut self, moved: impl Fn(usize) -> usize) {
+        self.start = moved(self.start);
+        self.cells.iter_mut().for_each(|cell| *cell = moved(*cell));
+    }
+}
+
+/// A grid being built, along with what is needed to keep linking equations to it.
+#[derive(Clone)]
+pub(crate) struct Layout {
+    pub grid: Grid,
+    pub equations: Vec<PlacedEquation>,
+    /// Numbers that can still be used to link a new equation
+    candidates: Vec<(usize, Direction)>,
+    /// Number of equations crossing each equation
+    intersections: Vec<u32>,
+    failures: u32,
+}
+
+impl Layout {
+    /** A grid holding a single random equation in its middle */
+    pub fn start(
+        width: u32,
+        height: u32,
+        settings: &DifficultySettings,
+        rng: &mut PuzzleRng,
+    ) -> Result<Self, CrossmathError> {
+        let mut grid = Grid::new(width, height);
+        let mut candidates = vec![];
+
+        let first = Equation::random(settings, rng).ok_or(CrossmathError::NoEquationPossible)?;
+        let start = grid.get_idx((width - first.len() as u32) / 2, height / 2);
+        let cells = insert_equation(&mut grid, start, Direction::Right, first, &mut candidates)?;
+
+        Ok(Self {
+            grid,
+            equations: vec![PlacedEquation::new(start, Direction::Right, cells)],
+            candidates,
+            intersections: vec![0],
+            failures: 0,
+        })
+    }
+
+    pub fn equation_count(&self) -> u32 {
+        self.equations.len() as u32
+    }
+
+    /// Tries once to link a new random equation on one of the numbers of the layout,
+    /// and returns the resulting layout when it fits.
+    pub fn extend(
+        &mut self,
+        settings: &DifficultySettings,
+        rng: &mut PuzzleRng,
+    ) -> Result<Option<Layout>, CrossmathError> {
+        if self.candidates.is_empty() {
+            return Ok(None);
+        }
+
+        //Pick a random number for the next equation
+        let idx = rng.gen_range(0..self.candidates.len() as u32) as usize;
+        let (chosen_position, current_dir) = self.candidates[idx];
+        let owner = self
+            .equations
+            .iter()
+            .position(|eq| eq.cells.contains(&chosen_position))
+            .expect("Grid creation : every number belongs to an equation.");
+
+        if self.intersections[owner] >= settings.max_intersections {
+            // This equation is crowded enough, its numbers can't be linked anymore
+            let cells = &self.equations[owner].cells;
+            self.candidates.retain(|x| !cells.contains(&x.0));
+            return Ok(None);
+        }
+
+        let base_nb = self.grid[chosen_position]
+            .value()
+            .ok_or(CrossmathError::NotANumber { position: chosen_position })?;
+
+        let term = get_random_term(settings.operands as usize, rng);
+        //Find equation
+        let Some(eq) = Equation::generate(term, base_nb, settings, rng) else {
+            return Ok(None);
+        };
+        let Some(dir) = is_insertion_possible(&self.grid, chosen_position, current_dir, term, &eq) else {
+            return Ok(None);
+        };
+        let start_position = eq.get_start_position(self.grid.width() as i32, dir, term, chosen_position);
+
+        //Insert
+        let mut next = self.clone();
+        next.failures = 0;
+        let cells = insert_equation(&mut next.grid, start_position, dir, eq, &mut next.candidates)?;
+        let mut placed = PlacedEquation::new(start_position, dir, cells);
+        // The operands of a commutative equation are read in the writing order, which
+        // may differ from the generated one
+        let shared = placed.numbers().iter().position(|&cell| cell == chosen_position).unwrap_or(term);
+        placed.link = Some((owner, shared));
+        placed.crossings.push(owner);
+        let added = next.equations.len();
+        next.equations.push(placed);
+        next.equations[owner].crossings.push(added);
+        next.intersections[owner] += 1;
+        next.intersections.push(1);
+
+        // The element used can't be reached again to link new equations
+        next.candidates.retain(|x| x.0 != chosen_position);
+
+        Ok(Some(next))
+    }
+
+    /// Records a failed attempt at extending the layout, and tells whether
+    /// it is time to give up on it.
+    pub fn fail(&mut self) -> bool {
+        self.failures += 1;
+        self.failures >= ATTEMPTS_BEFORE_BACKTRACKING || self.candidates.is_empty()
+    }
+}
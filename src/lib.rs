@@ -1,208 +1,331 @@
 mod equation;
 mod utils;
 mod insertion;
+mod grid;
+mod difficulty;
+mod config;
+mod error;
+mod layout;
+mod solver;
+mod check;
+mod view;
+mod save;
+mod share;
+mod session;
+mod render;
+#[cfg(feature = "pdf")]
+mod book;
 
 pub mod prelude {
     pub use crate::equation::*;
-    pub const MAX_NUMBER: u32 = 100;
+    pub use crate::difficulty::*;
+    pub use crate::config::*;
+    pub use crate::error::*;
+    pub use crate::layout::*;
+    pub use crate::solver::*;
+    pub use crate::check::*;
+    pub use crate::view::*;
+    pub use crate::save::SCHEMA_VERSION;
+    pub use crate::share::ShareCodeError;
+    pub use crate::session::*;
+    pub use crate::render::*;
+    #[cfg(feature = "pdf")]
+    pub use crate::book::*;
     pub use crate::utils::*;
     pub use std::fmt;
     pub use wasm_bindgen::prelude::*;
     pub use crate::insertion::*;
+    pub use crate::grid::*;
     pub use rand::Rng;
+    pub use rand::SeedableRng;
+    pub use rand::seq::SliceRandom;
+    pub use serde::{Deserialize, Serialize};
+    /// Every random choice made while building a puzzle goes through this generator,
+    /// so that a seed is enough to rebuild the exact same puzzle on any target.
+    /// Indexes are drawn from `u32` ranges : a `usize` range takes 64 bits of the
+    /// stream natively but only 32 in wasm.
+    pub type PuzzleRng = rand_chacha::ChaCha8Rng;
 }
 
 use prelude::*;
-use rand::Rng;
 
 extern crate web_sys;
 
+/// Attempts at linking a new equation allowed for each requested equation.
+const ATTEMPTS_PER_EQUATION: u32 = 200;
+/// Guesses the solver may try to prove a hidden cell keeps a single solution.
+/// Beyond it the cell stays shown, so that big grids can't stall the generation.
+const HIDING_MAX_NODES: u32 = 200;
+
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct Crossmath {
-    width: u32,
-    height: u32,
-    grid: Vec<String>,
+    grid: Grid,
+    equations: Vec<PlacedEquation>,
+    config: CrossmathConfig,
 }
 
 impl fmt::Display for Crossmath {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let mut rng = rand::thread_rng();
-        let mut hidden: Vec<String> = vec![];
-        for y in 0..self.height {
-            for x in 0..self.width {
-                let i = self.get_idx(x, y);
-                let mut symbol = self.grid[i as usize].clone();
-                if symbol.parse::<u32>().is_ok() {
-                    let proba: f64 = rng.gen();
-                    if proba < 0.4 {
-                        hidden.push(symbol.clone());
-                        symbol = "?".to_string();
-                    }
-                }
-                write!(f, "{};", symbol)?;
-            }
+        for cell in self.grid.iter() {
+            write!(f, "{};", cell)?;
         }
 
-        hidden.iter().for_each(|secret| write!(f, "{};", secret).unwrap());
+        for secret in self.hints() {
+            write!(f, "{};", secret)?;
+        }
 
         Ok(())
     }
 }
 
+impl fmt::Debug for Crossmath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Crossmath (seed {}, hints {:?})", self.seed(), self.hints())?;
+        write!(f, "{}", self.to_ascii(&AsciiOptions::new()))?;
+        writeln!(f, "Solution")?;
+        write!(f, "{}", self.to_ascii(&AsciiOptions::new().show_solution(true)))
+    }
+}
+
 #[wasm_bindgen]
 impl Crossmath {
-    pub fn new(number_of_equations: u32) -> Self {
+    pub fn new(number_of_equations: u32) -> Result<Crossmath, CrossmathError> {
+        Self::with_difficulty(number_of_equations, Difficulty::default())
+    }
+
+    pub fn from_seed(seed: u64, number_of_equations: u32) -> Result<Crossmath, CrossmathError> {
+        Self::from_seed_with_difficulty(seed, number_of_equations, Difficulty::default())
+    }
+
+    pub fn with_difficulty(
+        number_of_equations: u32,
+        difficulty: Difficulty,
+    ) -> Result<Crossmath, CrossmathError> {
+        Self::from_seed_with_difficulty(rand::thread_rng().gen(), number_of_equations, difficulty)
+    }
+
+    pub fn from_seed_with_difficulty(
+        seed: u64,
+        number_of_equations: u32,
+        difficulty: Difficulty,
+    ) -> Result<Crossmath, CrossmathError> {
+        let config = CrossmathConfig::from_difficulty(difficulty)
+            .equations(number_of_equations)
+            .seed(seed);
+        Self::with_config(&config)
+    }
+
+    pub fn with_config(config: &CrossmathConfig) -> Result<Crossmath, CrossmathError> {
         // set_panic_hook();
 
-        if number_of_equations <= 0 {
-            panic!(
-                "Can't create less than 1 equation for the game, got {}.",
-                number_of_equations
-            );
-        }
+        config.validate()?;
 
-        let size = 2 * 5 * number_of_equations;
+        let seed = config.seed.unwrap_or_else(|| rand::thread_rng().gen());
+        let (width, height) = config.grid_size();
 
         let mut crossmath = Self {
-            width: size,
-            height: size,
-            grid: vec![],
+            grid: Grid::new(width, height),
+            equations: vec![],
+            config: config.clone().seed(seed),
         };
 
-        crossmath.create_grid(number_of_equations);
+        let mut rng = PuzzleRng::seed_from_u64(seed);
+        let mut equations = crossmath.create_grid(&mut rng)?;
+        // The solver goes through every cell, the empty margins would only slow it down
+        let moved = crossmath.grid.crop();
+        equations.iter_mut().for_each(|eq| eq.reindex(&moved));
+        crossmath.hide_cells(&equations, &mut rng);
+        crossmath.equations = equations;
+
+        Ok(crossmath)
+    }
+
+    /** The whole puzzle as JSON, see `SCHEMA_VERSION` */
+    pub fn to_json(&self) -> Result<String, CrossmathError> {
+        self.save()
+    }
+
+    pub fn from_json(json: &str) -> Result<Crossmath, CrossmathError> {
+        Self::load(json)
+    }
+
+    /** A short URL-safe code to generate the same puzzle again */
+    pub fn to_share_code(&self) -> String {
+        self.share_code()
+    }
 
-        crossmath
+    pub fn from_share_code(code: &str) -> Result<Crossmath, CrossmathError> {
+        let config = Self::config_from_share_code(code)?;
+        Self::with_config(&config)
     }
 
     pub fn width(&self) -> u32 {
-        self.width
+        self.grid.width()
     }
 
     pub fn height(&self) -> u32 {
-        self.height
+        self.grid.height()
     }
 
-    pub fn check(&self, player_answer: String) -> bool {
-        let player_answer: Vec<&str> = player_answer.split(';').collect();
-        if player_answer.len() != self.grid.len() + 1 {
-            return false;
-        }
-        for (index, cell) in self.grid.iter().enumerate() {
-            if *cell != player_answer[index] {
-                return false;
-            }
+    pub fn seed(&self) -> u64 {
+        self.config
+            .seed
+            .expect("The seed is always set once the puzzle is generated")
+    }
+
+    /** Indexes of the cells the player has to fill */
+    pub fn hidden_cells(&self) -> Vec<u32> {
+        self.grid
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.is_hidden())
+            .map(|(idx, _)| idx as u32)
+            .collect()
+    }
+
+    /** The cell at column x and row y, undefined outside of the grid */
+    pub fn cell(&self, x: u32, y: u32) -> Option<CellView> {
+        if x >= self.width() || y >= self.height() {
+            return None;
         }
-        true
+        Some(CellView::new(&self.grid, self.get_idx(x, y)))
     }
 
-    fn create_grid(&mut self, nb_of_equations: u32) {
-        let mut grid: Vec<String> = (0..self.width * self.height)
-            .map(|_| " ".to_string())
-            .collect();
+    /** Every cell of the grid, row by row */
+    pub fn cells(&self) -> Vec<CellView> {
+        (0..self.grid.len()).map(|idx| CellView::new(&self.grid, idx)).collect()
+    }
 
-        let mut numbers_positions: Vec<(usize, Direction)> = vec![];
-        let mut every_number: Vec<usize> = vec![];
-
-        //Insert the first equation
-        insert_equation(
-            self.width as i32,
-            &mut grid,
-            self.get_idx(self.width / 2, self.height / 2),
-            Direction::Right,
-            Equation::random(),
-            &mut numbers_positions
-        );
-        let mut nb_equations = 1;
-
-        let mut rng = rand::thread_rng();
-        while nb_equations < nb_of_equations {
-            //Pick a random number for the next equation
-            let idx = rng.gen_range(0..numbers_positions.len());
-            let chosen_position = numbers_positions[idx].0;
-            let current_dir = numbers_positions[idx].1;
-            let base_nb = grid[chosen_position].clone();
-            println!("{}", base_nb);
-            let base_nb = base_nb
-                .trim()
-                .parse()
-                .expect(
-                    "Grid creation : the string {base_nb} cannot be converted to a u32."
-                );
-
-            let param = get_random_match_parameter();
-            //Find equation
-            let eq = Equation::generate(param, base_nb);
-
-            if let Some(dir) = is_insertion_possible(self.width as i32, &grid, chosen_position, current_dir, param, &eq) {
-                let start_position = eq.get_start_position(self.width as i32, dir, param, chosen_position);
-
-                //Insert
-                insert_equation(self.width as i32, &mut grid, start_position, dir, eq, &mut numbers_positions);
-
-                //Update equation count
-                nb_equations += 1;
-
-                every_number = numbers_positions.iter()
-                                .map(|x| x.0)
-                                .collect();
-                // The element used can't be reached again to link new equations
-                numbers_positions = numbers_positions
-                                        .iter()
-                                        .map(|x| (x.0, x.1))
-                                        .filter(|x| x.0 != chosen_position)
-                                        .collect();
-            }
+    #[wasm_bindgen(js_name = equations)]
+    pub fn equation_views(&self) -> Vec<EquationView> {
+        self.equations.iter().map(|eq| EquationView::new(&self.grid, eq)).collect()
+    }
+
+    /** Equations going through the cell at column x and row y */
+    pub fn equations_at(&self, x: u32, y: u32) -> Vec<u32> {
+        if x >= self.width() || y >= self.height() {
+            return vec![];
         }
+        let idx = self.get_idx(x, y);
+        (0..self.equations.len())
+            .filter(|&position| self.equations[position].cells.contains(&idx))
+            .map(|position| position as u32)
+            .collect()
+    }
 
-        self.grid = grid;
-        self.crop_grid(every_number);
+    /** Values removed from the grid, in grid order */
+    pub fn hints(&self) -> Vec<u32> {
+        self.grid
+            .iter()
+            .filter(|cell| cell.is_hidden())
+            .filter_map(|cell| cell.value())
+            .collect()
     }
 
-    fn crop_grid(&mut self, numbers_positions: Vec<usize>) {
-        let mut xmin = self.width;
-        let mut xmax = 0;
-        let mut ymin = self.height;
-        let mut ymax = 0;
+    /// Tells which cells and equations of the player's `;` separated answer are
+    /// right. Any answer using every hint once and making every equation hold is solved.
+    pub fn check(&self, player_answer: String) -> Result<CheckReport, CrossmathError> {
+        let values = parse_answer(&self.grid, &player_answer)?;
+        Ok(CheckReport::new(&self.grid, &self.equations, &values))
+    }
 
-        for position in numbers_positions {
-            let position = position as u32;
-            let (x, y) = (position % self.width, position / self.width);
-            if x < xmin {
-                xmin = x;
-            }
-            if x > xmax {
-                xmax = x
+    /// Places the equations in the grid, giving up on the last placed equation
+    /// when no other one can be linked to the grid anymore.
+    fn create_grid(&mut self, rng: &mut PuzzleRng) -> Result<Vec<PlacedEquation>, CrossmathError> {
+        let settings = &self.config.settings;
+        let requested = self.config.equations;
+        let (width, height) = (self.grid.width(), self.grid.height());
+
+        let mut stack = vec![Layout::start(width, height, settings, rng)?];
+        let mut best = stack[0].clone();
+        let mut attempts = 0;
+
+        while let Some(layout) = stack.last_mut() {
+            if layout.equation_count() >= requested {
+                break;
             }
-            if y < ymin {
-                ymin = y;
+            if attempts >= ATTEMPTS_PER_EQUATION * requested {
+                let placed = best.equation_count();
+                let mut partial = best.grid;
+                let _ = partial.crop();
+                return Err(CrossmathError::CouldNotPlace {
+                    requested,
+                    placed,
+                    partial,
+                });
             }
-            if y > ymax {
-                ymax = y
+            attempts += 1;
+
+            match layout.extend(settings, rng)? {
+                Some(next) => {
+                    if next.equation_count() > best.equation_count() {
+                        best = next.clone();
+                    }
+                    stack.push(next);
+                }
+                None => {
+                    if layout.fail() {
+                        // Backtrack, or start over from another first equation
+                        if stack.len() > 1 {
+                            stack.pop();
+                        } else {
+                            stack[0] = Layout::start(width, height, settings, rng)?;
+                        }
+                    }
+                }
             }
         }
 
-        let new_width = (xmax - xmin) + 1;
-        let new_height = (ymax - ymin) + 1;
+        let layout = stack.pop().expect("The first equation is never removed");
+        self.grid = layout.grid;
+        Ok(layout.equations)
+    }
 
-        let mut grid: Vec<String> = (0..new_width * new_height)
-            .map(|_| " ".to_string())
+    /// Hides numbers in a random order, keeping each one only if the puzzle
+    /// still has a single solution.
+    fn hide_cells(&mut self, equations: &[PlacedEquation], rng: &mut PuzzleRng) {
+        let settings = &self.config.settings;
+        let mut numbers: Vec<usize> = (0..self.grid.len())
+            .filter(|&idx| self.grid[idx].value().is_some())
             .collect();
+        numbers.shuffle(rng);
 
-        for x in xmin..=xmax {
-            for y in ymin..=ymax {
-                let new_idx = ((x - xmin) + ((y - ymin) * new_width)) as usize;
-                let idx = self.get_idx(x, y);
-                grid[new_idx] = self.grid[idx].clone();
+        let target = if settings.maximize_hidden {
+            numbers.len()
+        } else {
+            (numbers.len() as f64 * settings.hidden_ratio).round() as usize
+        };
+
+        let mut hidden = 0;
+        for idx in numbers {
+            if hidden >= target {
+                break;
+            }
+
+            let chained = equations
+                .iter()
+                .filter(|eq| eq.cells.contains(&idx))
+                .any(|eq| eq.cells.iter().any(|&other| self.grid[other].is_hidden()));
+            if chained && !settings.chained_hidden {
+                continue;
             }
-        }
 
-        self.grid = grid;
-        self.width = new_width;
-        self.height = new_height;
+            let revealed = self.grid[idx];
+            if let Cell::Number { value, .. } = revealed {
+                self.grid[idx] = Cell::Number { value, hidden: true };
+            }
+            let solver = Solver::new(&self.grid, equations).max_nodes(HIDING_MAX_NODES).trace(false);
+            if solver.solve().is_unique() {
+                hidden += 1;
+            } else {
+                self.grid[idx] = revealed;
+            }
+        }
     }
 
     pub fn get_idx(&self, x: u32, y: u32) -> usize {
-        (x + (y * self.width)) as usize
+        self.grid.get_idx(x, y)
     }
 
     pub fn render(&self) -> String {
@@ -210,19 +333,243 @@ impl Crossmath {
     }
 }
 
+impl Crossmath {
+    pub fn settings(&self) -> &DifficultySettings {
+        &self.config.settings
+    }
+
+    pub fn config(&self) -> &CrossmathConfig {
+        &self.config
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn equations(&self) -> &[PlacedEquation] {
+        &self.equations
+    }
+
+    /** Solves the puzzle as the player sees it, stopping after `max_solutions` solutions */
+    pub fn solve(&self, max_solutions: u32) -> SolverReport {
+        Solver::new(&self.grid, &self.equations)
+            .max_solutions(max_solutions)
+            .solve()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    #[should_panic]
     fn generation_safety() {
-        Crossmath::new(0); 
+        let error = Crossmath::new(0).err();
+        assert_eq!(error, Some(CrossmathError::InvalidConfig(ConfigError::NoEquation)));
     }
 
     #[test]
     fn generation_works() {
-        let entity = Crossmath::new(2);
+        let entity = Crossmath::new(2).unwrap();
         entity.render();
     }
+
+    #[test]
+    fn same_seed_same_puzzle() {
+        let first = Crossmath::from_seed(42, 6).unwrap();
+        let second = Crossmath::from_seed(42, 6).unwrap();
+        assert_eq!(first.grid, second.grid);
+        assert_eq!(first.render(), second.render());
+        assert_eq!(first.render(), first.render());
+        assert_eq!(first.seed(), 42);
+    }
+
+    /// Checked against the wasm build : random indexes taken from `usize` ranges
+    /// used to give another grid in the browser.
+    #[test]
+    fn same_seed_same_puzzle_on_every_target() {
+        let rendered = Crossmath::from_seed(42, 6).unwrap().render();
+        assert_eq!(rendered, " ; ;194; ; ; ; ; ; ;-; ; ; ; ; ; ;2; ;198; ; ; ; ;=; ;÷; ; ; ; ;192;=;3;×;?; ; ; ; ;=; ;-;?;+;43;=;?; ;?; ; ;-; ; ; ;=; ; ;7; ; ; ;52; ; ;=; ; ; ; ; ; ;?; ; ; ; ;64;23;66;12;36;");
+    }
+
+    #[test]
+    fn hidden_cells_are_chosen_once() {
+        let entity = Crossmath::from_seed(7, 6).unwrap();
+        let hidden = entity.hidden_cells();
+        let hints = entity.hints();
+        assert_eq!(hidden.len(), hints.len());
+        for (idx, hint) in hidden.iter().zip(&hints) {
+            assert_eq!(entity.grid[*idx as usize].value(), Some(*hint));
+        }
+
+        let tokens: Vec<String> = entity.render().split(';').map(String::from).collect();
+        let grid_len = entity.grid.len();
+        for idx in &hidden {
+            assert_eq!(tokens[*idx as usize], "?");
+        }
+        assert_eq!(tokens[grid_len..grid_len + hints.len()].join(";"),
+            hints.iter().map(|hint| hint.to_string()).collect::<Vec<_>>().join(";"));
+        assert_eq!(entity.render(), entity.render());
+    }
+
+    #[test]
+    fn config_drives_generation() {
+        let config = CrossmathConfig::new()
+            .equations(4)
+            .max_width(15)
+            .max_height(15)
+            .seed(5);
+        let entity = Crossmath::with_config(&config).unwrap();
+        assert!(entity.width() <= 15 && entity.height() <= 15);
+        assert_eq!(entity.seed(), 5);
+        assert_eq!(entity.render(), Crossmath::with_config(&config).unwrap().render());
+
+        let error = Crossmath::with_config(&config.equations(0)).err();
+        assert_eq!(error, Some(CrossmathError::InvalidConfig(ConfigError::NoEquation)));
+    }
+
+    #[test]
+    fn difficulty_drives_generation() {
+        for seed in 0..20 {
+            let entity = Crossmath::from_seed_with_difficulty(seed, 5, Difficulty::Easy).unwrap();
+            let settings = entity.settings();
+            for cell in entity.grid.iter() {
+                match cell {
+                    Cell::Operator(operation) => {
+                        assert!(matches!(operation, Operation::Plus | Operation::Minus))
+                    }
+                    Cell::Number { value, .. } => assert!(*value <= settings.max_result),
+                    _ => {}
+                }
+            }
+        }
+
+        let config = CrossmathConfig::from_difficulty(Difficulty::Easy)
+            .hidden_ratio(1.0)
+            .equations(5)
+            .seed(3);
+        let entity = Crossmath::with_config(&config).unwrap();
+        let hidden = entity.hidden_cells().len();
+        assert!((1..=5).contains(&hidden), "{} cells hidden for 5 equations", hidden);
+    }
+
+    #[test]
+    fn generation_always_terminates() {
+        let difficulties = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert];
+        for difficulty in difficulties {
+            for seed in 0..1000 {
+                let config = CrossmathConfig::from_difficulty(difficulty).equations(8).seed(seed);
+                match Crossmath::with_config(&config) {
+                    Ok(entity) => assert_eq!(entity.hints().len(), entity.hidden_cells().len()),
+                    Err(error) => panic!("{:?} seed {} : {}", difficulty, seed, error),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn crowded_grid_reports_partial_grid() {
+        let config = CrossmathConfig::new()
+            .equations(10)
+            .max_width(9)
+            .max_height(9)
+            .seed(2);
+        match Crossmath::with_config(&config) {
+            Err(CrossmathError::CouldNotPlace { requested, placed, partial }) => {
+                assert_eq!(requested, 10);
+                assert!((1..10).contains(&placed));
+                assert!(partial.width() <= 9 && partial.height() <= 9);
+                assert!(partial.iter().any(|cell| cell.value().is_some()));
+            }
+            _ => panic!("10 equations don't fit in a 9x9 grid with this seed"),
+        }
+    }
+
+    #[test]
+    fn biggest_grids_are_built_quickly() {
+        let config = CrossmathConfig::from_difficulty(Difficulty::Expert)
+            .equations(MAX_EQUATIONS)
+            .operands(MAX_OPERANDS)
+            .maximize_hidden(true)
+            .seed(0);
+        let start = std::time::Instant::now();
+        let entity = Crossmath::with_config(&config).unwrap();
+        assert!(start.elapsed().as_secs() < 30, "{:?}", start.elapsed());
+        assert!(!entity.hints().is_empty());
+        assert!(entity.solve(2).is_unique());
+    }
+
+    #[test]
+    fn hidden_cells_keep_a_single_solution() {
+        let difficulties = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert];
+        for difficulty in difficulties {
+            for seed in 0..30 {
+                let config = CrossmathConfig::from_difficulty(difficulty).equations(8).seed(seed);
+                let entity = Crossmath::with_config(&config).unwrap();
+                assert!(entity.solve(2).is_unique(), "{:?}\n{:?}", difficulty, entity);
+
+                let maximized = Crossmath::with_config(&config.maximize_hidden(true)).unwrap();
+                assert!(maximized.solve(2).is_unique(), "{:?}\n{:?}", difficulty, maximized);
+                assert!(maximized.hints().len() >= entity.hints().len());
+            }
+        }
+    }
+
+    #[test]
+    fn check_accepts_any_valid_answer() {
+        let (grid, equations) = Grid::sample(5, 1, vec![(0, Equation::new(5, 7, Operation::Plus).unwrap())], &[0, 2]);
+        let entity = Crossmath {
+            grid,
+            equations,
+            config: CrossmathConfig::default(),
+        };
+
+        let solved = |answer: &str| entity.check(answer.to_string()).unwrap().is_solved();
+        assert!(solved("5;+;7;=;12;"));
+        assert!(solved("7;+;5;=;12;"));
+        // The equation holds, but the hints are not the ones given
+        assert!(!solved("6;+;6;=;12;"));
+        assert!(!solved("5;+;;=;12;"));
+        assert!(entity.check("5;+;7;=".to_string()).is_err());
+
+        let generated = Crossmath::from_seed(4, 6).unwrap();
+        let answer: String = generated.grid.iter().map(|cell| format!("{};", cell.revealed())).collect();
+        assert!(generated.check(answer).unwrap().is_solved());
+    }
+
+    #[test]
+    fn longer_equations_build_solvable_grids() {
+        for (difficulty, operands) in [(Difficulty::Easy, 3), (Difficulty::Medium, 3), (Difficulty::Hard, 4)] {
+            for seed in 0..10 {
+                let config = CrossmathConfig::from_difficulty(difficulty).operands(operands).equations(6).seed(seed);
+                let entity = Crossmath::with_config(&config).unwrap();
+                let length = 2 * operands as usize + 1;
+                assert!(entity.equations().iter().all(|eq| eq.cells.len() == length), "{:?}", entity);
+                assert!(entity.solve(2).is_unique(), "{:?}", entity);
+
+                let answer: String = entity.grid.iter().map(|cell| format!("{};", cell.revealed())).collect();
+                assert!(entity.check(answer).unwrap().is_solved(), "{:?}", entity);
+            }
+        }
+    }
+
+    #[test]
+    fn equations_remember_how_they_were_linked() {
+        for seed in 0..50 {
+            let entity = Crossmath::from_seed(seed, 8).unwrap();
+            let equations = entity.equations();
+            assert_eq!(equations[0].link, None);
+            for (position, eq) in equations.iter().enumerate().skip(1) {
+                let (owner, param) = eq.link.expect("Every equation but the first is linked");
+                assert!(owner < position);
+                assert!(equations[owner].cells.contains(&eq.term(param)), "{:?}", entity);
+                assert!(eq.crossings.contains(&owner));
+                assert!(equations[owner].crossings.contains(&position));
+
+                let (x, y) = entity.grid().coordinates(eq.term(param));
+                let through = entity.equations_at(x, y);
+                assert!(through.contains(&(owner as u32)) && through.contains(&(position as u32)));
+            }
+        }
+    }
 }
@@ -0,0 +1,476 @@
+use std::fmt::Write;
+
+use crate::prelude::*;
+use crate::Crossmath;
+
+const NUMBER_FILL: Color = Color(0xFC, 0xED, 0xBE);
+const NUMBER_STROKE: Color = Color(0xB9, 0x7A, 0x57);
+const HIDDEN_FILL: Color = Color(0xFF, 0xFF, 0xFF);
+const HINT_FILL: Color = Color(0x16, 0x25, 0x21);
+const TEXT: Color = Color(0x16, 0x25, 0x21);
+const SOLUTION_TEXT: Color = Color(0xB3, 0x26, 0x1E);
+const WHITE: Color = Color(0xFF, 0xFF, 0xFF);
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Color(pub u8, pub u8, pub u8);
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02X}{:02X}{:02X}", self.0, self.1, self.2)
+    }
+}
+
+/// How a text is placed around its position.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Anchor {
+    Start,
+    Middle,
+}
+
+/// A vector primitive, in points from the top left corner of the drawing.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Shape {
+    Rect {
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+        fill: Color,
+        stroke: Option<Color>,
+    },
+    /** `y` is the middle of the text height, not its baseline */
+    Text {
+        x: f64,
+        y: f64,
+        size: f64,
+        content: String,
+        color: Color,
+        bold: bool,
+        anchor: Anchor,
+    },
+}
+
+/// Shapes making a picture, that any vector format can be written from.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Drawing {
+    pub width: f64,
+    pub height: f64,
+    pub font_family: String,
+    pub shapes: Vec<Shape>,
+}
+
+impl Drawing {
+    pub fn to_svg(&self) -> String {
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="{font}">"#,
+            w = self.width,
+            h = self.height,
+            font = escape(&self.font_family),
+        );
+        svg.push('\n');
+        for shape in &self.shapes {
+            let _ = match shape {
+                Shape::Rect { x, y, width, height, fill, stroke } => {
+                    let stroke = stroke.map_or(String::new(), |stroke| format!(r#" stroke="{}""#, stroke));
+                    writeln!(
+                        svg,
+                        r#"  <rect x="{}" y="{}" width="{}" height="{}" fill="{}"{}/>"#,
+                        x, y, width, height, fill, stroke
+                    )
+                }
+                Shape::Text { x, y, size, content, color, bold, anchor } => {
+                    let anchor = match anchor {
+                        Anchor::Start => "start",
+                        Anchor::Middle => "middle",
+                    };
+                    let weight = if *bold { r#" font-weight="bold""# } else { "" };
+                    writeln!(
+                        svg,
+                        r#"  <text x="{}" y="{}" font-size="{}" fill="{}" text-anchor="{}" dominant-baseline="central"{}>{}</text>"#,
+                        x,
+                        y,
+                        size,
+                        color,
+                        anchor,
+                        weight,
+                        escape(content)
+                    )
+                }
+            };
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// What to draw, and how big.
+///
+/// ```
+/// use crossmath::prelude::*;
+/// use crossmath::Crossmath;
+///
+/// let crossmath = Crossmath::from_seed(4, 5).unwrap();
+/// let svg = crossmath.to_svg(&RenderOptions::new().cell_size(30.0).title("Week 12".to_string()));
+/// assert!(svg.starts_with("<svg"));
+/// ```
+#[wasm_bindgen]
+#[derive(Clone, PartialEq, Debug)]
+pub struct RenderOptions {
+    pub(crate) cell_size: f64,
+    pub(crate) font_family: String,
+    /** Relative to the cell size */
+    pub(crate) font_scale: f64,
+    pub(crate) show_solution: bool,
+    pub(crate) show_hints: bool,
+    pub(crate) show_seed: bool,
+    pub(crate) title: Option<String>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            cell_size: 40.0,
+            font_family: "Helvetica, Arial, sans-serif".to_string(),
+            font_scale: 0.45,
+            show_solution: false,
+            show_hints: true,
+            show_seed: false,
+            title: None,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RenderOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /** Side of a cell, in points */
+    pub fn cell_size(mut self, size: f64) -> Self {
+        self.cell_size = size.max(1.0);
+        self
+    }
+
+    pub fn font_family(mut self, family: String) -> Self {
+        self.font_family = family;
+        self
+    }
+
+    /** Height of the numbers, as a part of the cell size */
+    pub fn font_scale(mut self, scale: f64) -> Self {
+        self.font_scale = scale.max(0.0);
+        self
+    }
+
+    /** Writes the value of the hidden cells, in another color */
+    pub fn show_solution(mut self, show: bool) -> Self {
+        self.show_solution = show;
+        self
+    }
+
+    pub fn show_hints(mut self, show: bool) -> Self {
+        self.show_hints = show;
+        self
+    }
+
+    /** Writes the seed under the grid, to generate the puzzle again */
+    pub fn show_seed(mut self, show: bool) -> Self {
+        self.show_seed = show;
+        self
+    }
+
+    pub fn title(mut self, title: String) -> Self {
+        self.title = Some(title);
+        self
+    }
+}
+
+/// What to write in a text grid, see `Crossmath::to_ascii`.
+#[wasm_bindgen]
+#[derive(Clone, PartialEq, Debug)]
+pub struct AsciiOptions {
+    pub(crate) show_solution: bool,
+    pub(crate) show_coordinates: bool,
+    /** Written in hidden cells holding no entry */
+    pub(crate) placeholder: String,
+    /** Values written by the player, shown in place of their hidden cell */
+    pub(crate) entries: Vec<(usize, u32)>,
+}
+
+impl Default for AsciiOptions {
+    fn default() -> Self {
+        Self {
+            show_solution: false,
+            show_coordinates: false,
+            placeholder: "?".to_string(),
+            entries: vec![],
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl AsciiOptions {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /** Writes the value of the hidden cells without an entry */
+    pub fn show_solution(mut self, show: bool) -> Self {
+        self.show_solution = show;
+        self
+    }
+
+    /** Numbers the columns above the grid and the rows on its left */
+    pub fn show_coordinates(mut self, show: bool) -> Self {
+        self.show_coordinates = show;
+        self
+    }
+
+    pub fn placeholder(mut self, placeholder: String) -> Self {
+        self.placeholder = placeholder;
+        self
+    }
+
+    pub fn entry(mut self, cell: usize, value: u32) -> Self {
+        self.entries.push((cell, value));
+        self
+    }
+}
+
+impl AsciiOptions {
+    pub fn entries(mut self, entries: impl IntoIterator<Item = (usize, u32)>) -> Self {
+        self.entries.extend(entries);
+        self
+    }
+}
+
+#[wasm_bindgen]
+impl Crossmath {
+    /** A standalone SVG picture of the puzzle */
+    pub fn to_svg(&self, options: &RenderOptions) -> String {
+        self.drawing(options).to_svg()
+    }
+
+    /// The grid as aligned columns of text, every column as wide as the biggest number
+    /// the settings allow.
+    pub fn to_ascii(&self, options: &AsciiOptions) -> String {
+        let grid = self.grid();
+        let size = self.ascii_width(options);
+
+        let mut lines = vec![];
+        if options.show_coordinates {
+            let columns: Vec<String> = (0..grid.width()).map(|x| format!("{:>size$}", x)).collect();
+            lines.push(format!("    {}", columns.join(" ")));
+        }
+        for y in 0..grid.height() {
+            let row: Vec<String> = (0..grid.width())
+                .map(|x| format!("{:>size$}", self.ascii_cell(grid.get_idx(x, y), options)))
+                .collect();
+            let row = if options.show_coordinates {
+                format!("{:>3} {}", y, row.join(" "))
+            } else {
+                row.join(" ")
+            };
+            lines.push(row.trim_end().to_string());
+        }
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+}
+
+impl Crossmath {
+    /** Text of a cell in `to_ascii`, without padding */
+    pub fn ascii_cell(&self, idx: usize, options: &AsciiOptions) -> String {
+        let cell = self.grid()[idx];
+        if let Some((_, value)) = options.entries.iter().rev().find(|(cell, _)| *cell == idx) {
+            return value.to_string();
+        }
+        match cell {
+            Cell::Number { value, hidden: true } if options.show_solution => value.to_string(),
+            Cell::Number { hidden: true, .. } => options.placeholder.clone(),
+            _ => cell.to_string(),
+        }
+    }
+
+    /** Width of the columns of `to_ascii` : the biggest number allowed, or written */
+    pub fn ascii_width(&self, options: &AsciiOptions) -> usize {
+        let written = (0..self.grid().len()).map(|idx| self.ascii_cell(idx, options).chars().count());
+        written.max().unwrap_or(1).max(self.settings().max_result.to_string().len())
+    }
+}
+
+impl Crossmath {
+    /// The puzzle as vector shapes : the title, the grid, the hint bank and the seed,
+    /// from top to bottom.
+    pub fn drawing(&self, options: &RenderOptions) -> Drawing {
+        let cell = options.cell_size;
+        let margin = cell / 2.0;
+        let font = cell * options.font_scale;
+        let grid = self.grid();
+        let width = (grid.width() as f64 * cell).max(cell * 6.0) + 2.0 * margin;
+
+        let mut shapes = vec![];
+        let mut top = margin;
+        let text = |x: f64, y: f64, size: f64, content: String, color: Color, bold: bool, anchor: Anchor| {
+            Shape::Text { x, y, size, content, color, bold, anchor }
+        };
+
+        if let Some(title) = &options.title {
+            shapes.push(text(margin, top + cell / 2.0, cell * 0.6, title.clone(), TEXT, true, Anchor::Start));
+            top += cell * 1.5;
+        }
+
+        for (idx, &content) in grid.iter().enumerate() {
+            if content.is_empty() {
+                continue;
+            }
+            let (x, y) = grid.coordinates(idx);
+            let (x, y) = (margin + x as f64 * cell, top + y as f64 * cell);
+            let (fill, label, color) = match content {
+                Cell::Number { value, hidden: true } => {
+                    let label = if options.show_solution { value.to_string() } else { String::new() };
+                    (HIDDEN_FILL, label, SOLUTION_TEXT)
+                }
+                _ => (NUMBER_FILL, content.to_string(), TEXT),
+            };
+            shapes.push(Shape::Rect {
+                x,
+                y,
+                width: cell,
+                height: cell,
+                fill,
+                stroke: Some(if content.is_hidden() { TEXT } else { NUMBER_STROKE }),
+            });
+            if !label.is_empty() {
+                let bold = content.is_hidden();
+                shapes.push(text(x + cell / 2.0, y + cell / 2.0, font, label, color, bold, Anchor::Middle));
+            }
+        }
+        top += grid.height() as f64 * cell;
+
+        if options.show_hints {
+            top += cell / 2.0;
+            shapes.push(text(margin, top + cell / 4.0, font, "Hints".to_string(), TEXT, true, Anchor::Start));
+            top += cell / 2.0;
+            let per_row = (((width - 2.0 * margin) / (cell * 1.25)) as usize).max(1);
+            let hints = self.hints();
+            for (position, hint) in hints.iter().enumerate() {
+                let x = margin + (position % per_row) as f64 * cell * 1.25;
+                let y = top + (position / per_row) as f64 * cell * 1.25;
+                shapes.push(Shape::Rect {
+                    x,
+                    y,
+                    width: cell,
+                    height: cell,
+                    fill: HINT_FILL,
+                    stroke: None,
+                });
+                shapes.push(text(x + cell / 2.0, y + cell / 2.0, font, hint.to_string(), WHITE, false, Anchor::Middle));
+            }
+            let rows = hints.len().div_ceil(per_row);
+            top += rows as f64 * cell * 1.25 - cell * 0.25;
+        }
+
+        if options.show_seed {
+            top += cell / 2.0;
+            let seed = format!("Seed {}", self.seed());
+            shapes.push(text(margin, top + cell / 4.0, font * 0.8, seed, TEXT, false, Anchor::Start));
+            top += cell / 2.0;
+        }
+
+        Drawing {
+            width,
+            height: top + margin,
+            font_family: options.font_family.clone(),
+            shapes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_shows_what_is_asked() {
+        let crossmath = Crossmath::from_seed(11, 5).unwrap();
+        let hidden = crossmath.hints();
+        let filled = crossmath.grid().iter().filter(|cell| !cell.is_empty()).count();
+
+        let svg = crossmath.to_svg(&RenderOptions::new());
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), filled + hidden.len());
+        assert!(!svg.contains("Seed"));
+
+        let options = RenderOptions::new()
+            .show_hints(false)
+            .show_solution(true)
+            .show_seed(true)
+            .title("Maths & co".to_string());
+        let drawing = crossmath.drawing(&options);
+        let solution = drawing
+            .shapes
+            .iter()
+            .filter(|shape| matches!(shape, Shape::Text { color, .. } if *color == SOLUTION_TEXT))
+            .count();
+        assert_eq!(solution, hidden.len());
+        let svg = drawing.to_svg();
+        assert!(svg.contains("Maths &amp; co"));
+        assert!(svg.contains("Seed 11"));
+        assert_eq!(svg.matches("<rect").count(), filled);
+    }
+
+    #[test]
+    fn ascii_columns_are_aligned() {
+        let crossmath = Crossmath::from_seed(7, 5).unwrap();
+        let size = crossmath.settings().max_result.to_string().len();
+        let puzzle = crossmath.to_ascii(&AsciiOptions::new());
+        let solution = crossmath.to_ascii(&AsciiOptions::new().show_solution(true));
+        assert_eq!(puzzle.lines().count(), crossmath.height() as usize);
+        assert_eq!(puzzle.matches('?').count(), crossmath.hints().len(), "{}", puzzle);
+        assert!(!solution.contains('?'), "{}", solution);
+        assert!(solution.contains('='));
+
+        // Every cell starts on the same column from one line to another
+        let width = crossmath.width() as usize * (size + 1) - 1;
+        for line in puzzle.lines().chain(solution.lines()) {
+            assert!(line.chars().count() <= width, "{}", line);
+            for (position, character) in line.chars().enumerate() {
+                if (position + 1) % (size + 1) == 0 {
+                    assert_eq!(character, ' ', "{}", line);
+                }
+            }
+        }
+
+        // Entries take the place of the hidden cells, under numbered columns
+        let hidden = crossmath.hidden_cells()[0] as usize;
+        let options = AsciiOptions::new().show_coordinates(true).placeholder("_".to_string()).entry(hidden, 12345);
+        let played = crossmath.to_ascii(&options);
+        assert_eq!(played.lines().count(), crossmath.height() as usize + 1);
+        assert!(played.lines().next().unwrap().trim_start().starts_with("0 "));
+        assert!(played.contains("12345"));
+        assert_eq!(played.matches('_').count(), crossmath.hints().len() - 1, "{}", played);
+    }
+
+    #[test]
+    fn drawing_scales_with_the_cell_size() {
+        let crossmath = Crossmath::from_seed(2, 4).unwrap();
+        let small = crossmath.drawing(&RenderOptions::new().cell_size(20.0));
+        let big = crossmath.drawing(&RenderOptions::new().cell_size(40.0));
+        assert!((big.width - 2.0 * small.width).abs() < 1e-9);
+        assert!((big.height - 2.0 * small.height).abs() < 1e-9);
+        assert!(small.shapes.iter().all(|shape| match shape {
+            Shape::Rect { x, y, width, height, .. } => x + width <= small.width && y + height <= small.height,
+            Shape::Text { x, y, .. } => *x <= small.width && *y <= small.height,
+        }));
+    }
+}
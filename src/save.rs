@@ -0,0 +1,150 @@
+use crate::prelude::*;
+use crate::Crossmath;
+
+/// Version of the JSON schema written by `to_json`, bumped on every incompatible change.
+pub const SCHEMA_VERSION: u32 = 2;
+/// Oldest version still read. Version 1 only had equations of two operands.
+const OLDEST_VERSION: u32 = 1;
+
+/// Everything needed to rebuild a puzzle, as stored in JSON.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SavedPuzzle {
+    version: u32,
+    width: u32,
+    height: u32,
+    /// Cells row by row, hidden numbers are flagged as such
+    cells: Vec<Cell>,
+    equations: Vec<PlacedEquation>,
+    /// Settings and seed the puzzle was built with. Older documents also hold the
+    /// seed at the top level, where it is ignored.
+    config: CrossmathConfig,
+}
+
+/// Only reads the version, so that the error is clear when the schema changed.
+#[derive(Deserialize)]
+struct Versioned {
+    version: u32,
+}
+
+impl SavedPuzzle {
+    pub fn new(crossmath: &Crossmath) -> Self {
+        Self {
+            version: SCHEMA_VERSION,
+            width: crossmath.grid.width(),
+            height: crossmath.grid.height(),
+            cells: crossmath.grid.cells().to_vec(),
+            equations: crossmath.equations.clone(),
+            config: crossmath.config.clone(),
+        }
+    }
+
+    /** The saved puzzle, once checked that its equations fit in its grid and its settings are valid */
+    pub fn restore(self) -> Result<Crossmath, CrossmathError> {
+        self.config.validate()?;
+        let grid = Grid::from_cells(self.width, self.height, self.cells)
+            .ok_or_else(|| CrossmathError::Json("The cells don't fill the grid.".to_string()))?;
+        let count = self.equations.len();
+        for eq in &self.equations {
+            let valid = eq.cells.len() >= 5
+                && eq.cells.len() % 2 == 1
+                && eq.cells.iter().all(|&cell| cell < grid.len())
+                && eq.crossings.iter().all(|&other| other < count)
+                && eq.link.is_none_or(|(owner, term)| owner < count && term <= eq.operand_count())
+                && eq.operators(&grid).is_some();
+            if !valid {
+                return Err(CrossmathError::Json(format!("Invalid equation starting at {}.", eq.start)));
+            }
+        }
+
+        if self.config.seed.is_none() {
+            return Err(CrossmathError::Json("Missing seed.".to_string()));
+        }
+        Ok(Crossmath {
+            grid,
+            equations: self.equations,
+            config: self.config,
+        })
+    }
+}
+
+/// Writes any saved value as JSON.
+pub(crate) fn to_json(value: &impl Serialize) -> Result<String, CrossmathError> {
+    serde_json::to_string(value).map_err(|error| CrossmathError::Json(error.to_string()))
+}
+
+/// Reads a saved value, after making sure its schema is one that can be read.
+pub(crate) fn from_json<T: for<'de> Deserialize<'de>>(json: &str) -> Result<T, CrossmathError> {
+    let Versioned { version } =
+        serde_json::from_str(json).map_err(|error| CrossmathError::Json(error.to_string()))?;
+    if !(OLDEST_VERSION..=SCHEMA_VERSION).contains(&version) {
+        return Err(CrossmathError::UnsupportedVersion {
+            found: version,
+            supported: SCHEMA_VERSION,
+        });
+    }
+    serde_json::from_str(json).map_err(|error| CrossmathError::Json(error.to_string()))
+}
+
+impl Crossmath {
+    pub(crate) fn save(&self) -> Result<String, CrossmathError> {
+        to_json(&SavedPuzzle::new(self))
+    }
+
+    pub(crate) fn load(json: &str) -> Result<Self, CrossmathError> {
+        from_json::<SavedPuzzle>(json)?.restore()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn puzzles_survive_a_round_trip() {
+        let config = CrossmathConfig::from_difficulty(Difficulty::Hard).equations(7).seed(21);
+        let entity = Crossmath::with_config(&config).unwrap();
+        let json = entity.to_json().unwrap();
+        let loaded = Crossmath::from_json(&json).unwrap();
+        assert_eq!(loaded.grid(), entity.grid());
+        assert_eq!(loaded.equations(), entity.equations());
+        assert_eq!(loaded.config(), entity.config());
+        assert_eq!(loaded.seed(), 21);
+        assert_eq!(loaded.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn first_version_can_be_read() {
+        let json = r#"{"version":1,"width":5,"height":5,"cells":[{"Number":{"value":11,"hidden":false}},{"Operator":"Times"},{"Number":{"value":12,"hidden":false}},"Equals",{"Number":{"value":132,"hidden":true}},"Empty","Empty","Empty","Empty",{"Operator":"Plus"},"Empty","Empty","Empty","Empty",{"Number":{"value":54,"hidden":false}},"Empty","Empty","Empty","Empty","Equals","Empty","Empty","Empty","Empty",{"Number":{"value":186,"hidden":true}}],"equations":[{"start":0,"direction":"Right","cells":[0,1,2,3,4],"link":null,"crossings":[1]},{"start":4,"direction":"Down","cells":[4,9,14,19,24],"link":[0,"X"],"crossings":[0]}],"seed":3,"config":{"equations":2,"settings":{"min_number":1,"max_number":99,"max_result":200,"operators":[["Plus",1],["Minus",1],["Times",1],["Divide",1]],"hidden_ratio":0.4,"max_intersections":3,"chained_hidden":true,"maximize_hidden":false},"max_width":null,"max_height":null,"seed":3}}"#;
+        let loaded = Crossmath::from_json(json).unwrap();
+        assert_eq!(loaded.settings().operands, 2);
+        assert_eq!(loaded.equations()[1].link, Some((0, 0)));
+        assert_eq!(loaded.hidden_cells(), [4, 24]);
+        assert!(loaded.to_json().unwrap().starts_with("{\"version\":2,"));
+    }
+
+    #[test]
+    fn broken_json_is_rejected() {
+        let json = Crossmath::from_seed(3, 4).unwrap().to_json().unwrap();
+        let newer = json.replacen("\"version\":2", "\"version\":3", 1);
+        assert_eq!(
+            Crossmath::from_json(&newer).err(),
+            Some(CrossmathError::UnsupportedVersion { found: 3, supported: 2 })
+        );
+        assert!(matches!(Crossmath::from_json("{\"version\":2}"), Err(CrossmathError::Json(_))));
+
+        let wider = json.replacen("\"width\":", "\"width\":1", 1);
+        assert!(matches!(Crossmath::from_json(&wider), Err(CrossmathError::Json(_))));
+
+        assert_eq!(json.matches("\"seed\":").count(), 1);
+        let seedless = json.replace("\"seed\":3", "\"seed\":null");
+        assert_ne!(seedless, json);
+        assert_eq!(
+            Crossmath::from_json(&seedless).err(),
+            Some(CrossmathError::Json("Missing seed.".to_string()))
+        );
+
+        let empty = json.replacen("\"equations\":4", "\"equations\":0", 1);
+        assert_ne!(empty, json);
+        assert_eq!(Crossmath::from_json(&empty).err(), Some(CrossmathError::InvalidConfig(ConfigError::NoEquation)));
+    }
+}
@@ -0,0 +1,578 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::prelude::*;
+use crate::save::{from_json, to_json, SavedPuzzle};
+use crate::Crossmath;
+
+/// Points earned for each hidden cell, before penalties.
+const POINTS_PER_CELL: u32 = 100;
+/// Points lost for each hint, by tier.
+const HINT_PENALTIES: [u32; 3] = [25, 50, 100];
+const WRONG_ENTRY_PENALTY: u32 = 20;
+/// Points lost for each check after the first one.
+const CHECK_PENALTY: u32 = 10;
+/// Time given to find each hidden cell, a point is lost every 10 seconds over it.
+const SECONDS_PER_CELL: f64 = 30.0;
+
+/// A change made by the player, kept to be undone or redone.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Edit {
+    cell: usize,
+    before: Option<u32>,
+    after: Option<u32>,
+}
+
+/// How much a hint tells the player, from a nudge to the answer itself.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HintTier {
+    /** Points to a cell that can be found right now */
+    Cell,
+    /** Also tells which equation gives that cell */
+    Equation,
+    /** Writes the value of the cell in the grid */
+    Value,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hint {
+    pub tier: HintTier,
+    pub cell: usize,
+    /** Index of the equation to look at, from the `Equation` tier */
+    pub equation: Option<u32>,
+    /** Value of the cell, for the `Value` tier */
+    pub value: Option<u32>,
+}
+
+/// A puzzle being played : the values the player wrote in the hidden cells,
+/// and the history of their changes.
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct GameSession {
+    crossmath: Crossmath,
+    entries: BTreeMap<usize, u32>,
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    /** Hints given, by tier */
+    hints_used: [u32; 3],
+    /** Time played before the last pause, in milliseconds */
+    elapsed: f64,
+    /** When the clock was last resumed, `None` while paused */
+    running_since: Option<f64>,
+    checks: u32,
+    /** Wrong values found by the checks, each one counted once */
+    wrong_entries: u32,
+    /** Cells and values already counted in `wrong_entries` */
+    mistakes: BTreeSet<(usize, u32)>,
+}
+
+/// A session as stored in JSON. The history of changes isn't kept.
+#[derive(Serialize, Deserialize)]
+struct SavedSession {
+    version: u32,
+    puzzle: SavedPuzzle,
+    entries: BTreeMap<usize, u32>,
+    hints_used: [u32; 3],
+    elapsed: f64,
+    checks: u32,
+    wrong_entries: u32,
+    #[serde(default)]
+    mistakes: BTreeSet<(usize, u32)>,
+}
+
+#[wasm_bindgen]
+impl GameSession {
+    #[wasm_bindgen(constructor)]
+    pub fn new(crossmath: Crossmath) -> Self {
+        Self {
+            crossmath,
+            entries: BTreeMap::new(),
+            undo: vec![],
+            redo: vec![],
+            hints_used: [0; 3],
+            elapsed: 0.0,
+            running_since: None,
+            checks: 0,
+            wrong_entries: 0,
+            mistakes: BTreeSet::new(),
+        }
+    }
+
+    /** A copy of the puzzle being played */
+    pub fn puzzle(&self) -> Crossmath {
+        self.crossmath.clone()
+    }
+
+    /** Values to write in the hidden cells, see `Crossmath::hints` */
+    pub fn hints(&self) -> Vec<u32> {
+        self.crossmath.hints()
+    }
+
+    pub fn hidden_cells(&self) -> Vec<u32> {
+        self.crossmath.hidden_cells()
+    }
+
+    #[wasm_bindgen(js_name = equations)]
+    pub fn equation_views(&self) -> Vec<EquationView> {
+        self.crossmath.equation_views()
+    }
+
+    /** Value written by the player in a cell */
+    pub fn entry(&self, cell: usize) -> Option<u32> {
+        self.entries.get(&cell).copied()
+    }
+
+    pub fn set_cell(&mut self, cell: usize, value: u32) -> Result<(), CrossmathError> {
+        self.edit(cell, Some(value))
+    }
+
+    pub fn clear_cell(&mut self, cell: usize) -> Result<(), CrossmathError> {
+        self.edit(cell, None)
+    }
+
+    /** Cancels the last change, returns false when there is nothing to undo */
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.undo.pop() else {
+            return false;
+        };
+        self.write(edit.cell, edit.before);
+        self.redo.push(edit);
+        true
+    }
+
+    /** Applies again the last change undone, returns false when there is none */
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        self.write(edit.cell, edit.after);
+        self.undo.push(edit);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Hints written somewhere in the grid, in the order of `hints()`. A value written
+    /// more often than it appears in the hints only consumes it once per appearance.
+    pub fn consumed_hints(&self) -> Vec<u32> {
+        let mut written: BTreeMap<u32, u32> = BTreeMap::new();
+        for value in self.entries.values() {
+            *written.entry(*value).or_insert(0) += 1;
+        }
+        self.crossmath
+            .hints()
+            .into_iter()
+            .filter(|hint| match written.get_mut(hint) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            })
+            .collect()
+    }
+
+    /** Whether each hint of `hints()` has been written somewhere in the grid */
+    pub fn consumed_mask(&self) -> Vec<u8> {
+        let mut consumed = self.consumed_hints().into_iter().peekable();
+        self.crossmath
+            .hints()
+            .into_iter()
+            .map(|hint| u8::from(consumed.next_if_eq(&hint).is_some()))
+            .collect()
+    }
+
+    /** Hints not written in the grid yet */
+    pub fn remaining_hints(&self) -> Vec<u32> {
+        let mask = self.consumed_mask();
+        self.crossmath
+            .hints()
+            .into_iter()
+            .zip(mask)
+            .filter(|(_, consumed)| *consumed == 0)
+            .map(|(hint, _)| hint)
+            .collect()
+    }
+
+    /// Cell the next hint will be about, without giving it : the easiest one to find
+    /// from what the player rightly wrote so far.
+    pub fn next_hint_cell(&self) -> Option<usize> {
+        self.next_hint().map(|(cell, _)| cell)
+    }
+
+    /// Helps with the easiest cell to find from what the player rightly wrote so far.
+    /// Returns nothing once every hidden cell holds its value.
+    pub fn hint(&mut self, tier: HintTier) -> Option<Hint> {
+        let (cell, equation) = self.next_hint()?;
+        let mut hint = Hint {
+            tier,
+            cell,
+            equation: None,
+            value: None,
+        };
+        if tier != HintTier::Cell {
+            hint.equation = Some(equation as u32);
+        }
+        if tier == HintTier::Value {
+            hint.value = self.crossmath.grid()[cell].value();
+            let _ = self.edit(cell, hint.value);
+        }
+        self.hints_used[tier as usize] = self.hints_used[tier as usize].saturating_add(1);
+        Some(hint)
+    }
+
+    /** Hints given so far, whatever their tier */
+    pub fn hints_used(&self) -> u32 {
+        self.hints_used.iter().fold(0, |total, &used| total.saturating_add(used))
+    }
+
+    pub fn hints_used_at(&self, tier: HintTier) -> u32 {
+        self.hints_used[tier as usize]
+    }
+
+    /// Values are only counted as mistakes here, so that typing a number digit by
+    /// digit costs nothing.
+    pub fn check(&mut self) -> CheckReport {
+        self.checks = self.checks.saturating_add(1);
+        let grid = self.crossmath.grid();
+        for (&cell, &value) in &self.entries {
+            if grid[cell].value() != Some(value) && self.mistakes.insert((cell, value)) {
+                self.wrong_entries = self.wrong_entries.saturating_add(1);
+            }
+        }
+        CheckReport::new(self.crossmath.grid(), self.crossmath.equations(), &self.values())
+    }
+
+    /// Starts the clock, or starts it again after a pause. Times are given by the
+    /// caller in milliseconds, `Date.now()` in a browser.
+    pub fn resume(&mut self, now: f64) {
+        if self.running_since.is_none() {
+            self.running_since = Some(now);
+        }
+    }
+
+    pub fn pause(&mut self, now: f64) {
+        self.elapsed = self.elapsed(now);
+        self.running_since = None;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.running_since.is_none()
+    }
+
+    /** Time played so far, in milliseconds */
+    pub fn elapsed(&self, now: f64) -> f64 {
+        let running = self.running_since.map_or(0.0, |since| (now - since).max(0.0));
+        self.elapsed + running
+    }
+
+    pub fn checks(&self) -> u32 {
+        self.checks
+    }
+
+    pub fn wrong_entries(&self) -> u32 {
+        self.wrong_entries
+    }
+
+    /// Points for each hidden cell minus penalties for hints, mistakes, checks and time,
+    /// multiplied by the weight of the difficulty.
+    /// Counters are summed with saturating arithmetic : a saved session may hold any value.
+    pub fn score(&self, now: f64) -> u32 {
+        let hidden = self.crossmath.hidden_cells().len() as u32;
+        let overtime = (self.elapsed(now) / 1000.0 - hidden as f64 * SECONDS_PER_CELL).max(0.0);
+        let penalties = self
+            .hints_used
+            .iter()
+            .zip(HINT_PENALTIES)
+            .map(|(used, penalty)| used.saturating_mul(penalty))
+            .chain([
+                self.wrong_entries.saturating_mul(WRONG_ENTRY_PENALTY),
+                self.checks.saturating_sub(1).saturating_mul(CHECK_PENALTY),
+                (overtime / 10.0) as u32,
+            ])
+            .fold(0u32, u32::saturating_add);
+
+        let points = hidden.saturating_mul(POINTS_PER_CELL).saturating_sub(penalties);
+        let weight = self.crossmath.config().get_difficulty().score_weight();
+        (points as f64 * weight).round() as u32
+    }
+
+    /** The puzzle, the entries and the statistics as JSON. The session is saved paused. */
+    pub fn to_json(&self, now: f64) -> Result<String, CrossmathError> {
+        to_json(&SavedSession {
+            version: SCHEMA_VERSION,
+            puzzle: SavedPuzzle::new(&self.crossmath),
+            entries: self.entries.clone(),
+            hints_used: self.hints_used,
+            elapsed: self.elapsed(now),
+            checks: self.checks,
+            wrong_entries: self.wrong_entries,
+            mistakes: self.mistakes.clone(),
+        })
+    }
+
+    pub fn from_json(json: &str) -> Result<GameSession, CrossmathError> {
+        let saved: SavedSession = from_json(json)?;
+        let mut session = GameSession::new(saved.puzzle.restore()?);
+        for (&cell, &value) in &saved.entries {
+            let grid = session.crossmath.grid();
+            if cell >= grid.len() || !grid[cell].is_hidden() {
+                return Err(CrossmathError::NotAHiddenCell { position: cell });
+            }
+            session.entries.insert(cell, value);
+        }
+        session.hints_used = saved.hints_used;
+        session.elapsed = saved.elapsed;
+        session.checks = saved.checks;
+        session.wrong_entries = saved.wrong_entries;
+        session.mistakes = saved.mistakes;
+        Ok(session)
+    }
+}
+
+impl GameSession {
+    pub fn crossmath(&self) -> &Crossmath {
+        &self.crossmath
+    }
+
+    /// Value of each cell of the grid as seen by the player : shown numbers, and what
+    /// they wrote in the hidden cells.
+    pub fn values(&self) -> Vec<Option<u32>> {
+        let grid = self.crossmath.grid();
+        (0..grid.len())
+            .map(|idx| match grid[idx] {
+                Cell::Number { hidden: true, .. } => self.entry(idx),
+                cell => cell.value(),
+            })
+            .collect()
+    }
+
+    /** Cell to look for, and an equation giving it */
+    fn next_hint(&self) -> Option<(usize, usize)> {
+        let grid = self.crossmath.grid();
+        let equations = self.crossmath.equations();
+
+        // Cells rightly filled are shown, so that only the other ones are looked for
+        let mut known = grid.clone();
+        for (&cell, &value) in &self.entries {
+            if known[cell].value() == Some(value) {
+                known[cell] = Cell::number(value);
+            }
+        }
+
+        match Solver::new(&known, equations).easiest_deduction() {
+            Some(Step::Deduced { cell, equation, .. }) => Some((cell, equation)),
+            _ => {
+                // No equation can be completed alone, any cell left will do
+                let cell = (0..known.len()).find(|&idx| known[idx].is_hidden())?;
+                let equation = equations.iter().position(|eq| eq.cells.contains(&cell))?;
+                Some((cell, equation))
+            }
+        }
+    }
+
+    fn edit(&mut self, cell: usize, after: Option<u32>) -> Result<(), CrossmathError> {
+        let grid = self.crossmath.grid();
+        if cell >= grid.len() || !grid[cell].is_hidden() {
+            return Err(CrossmathError::NotAHiddenCell { position: cell });
+        }
+        let before = self.entry(cell);
+        if before == after {
+            return Ok(());
+        }
+        self.write(cell, after);
+        self.undo.push(Edit { cell, before, after });
+        self.redo.clear();
+        Ok(())
+    }
+
+    fn write(&mut self, cell: usize, value: Option<u32>) {
+        match value {
+            Some(value) => self.entries.insert(cell, value),
+            None => self.entries.remove(&cell),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_can_be_undone_and_redone() {
+        let crossmath = Crossmath::from_seed(8, 5).unwrap();
+        let hidden: Vec<usize> = crossmath.hidden_cells().into_iter().map(|idx| idx as usize).collect();
+        let shown = (0..crossmath.grid().len()).find(|idx| !hidden.contains(idx)).unwrap();
+        let mut session = GameSession::new(crossmath);
+
+        assert_eq!(session.set_cell(shown, 3), Err(CrossmathError::NotAHiddenCell { position: shown }));
+        assert!(!session.undo());
+
+        session.set_cell(hidden[0], 3).unwrap();
+        session.set_cell(hidden[0], 4).unwrap();
+        session.clear_cell(hidden[0]).unwrap();
+        assert_eq!(session.entry(hidden[0]), None);
+        assert!(session.undo());
+        assert_eq!(session.entry(hidden[0]), Some(4));
+        assert!(session.undo());
+        assert_eq!(session.entry(hidden[0]), Some(3));
+        assert!(session.redo());
+        assert_eq!(session.entry(hidden[0]), Some(4));
+
+        // A new change forgets what was undone
+        session.set_cell(hidden[0], 5).unwrap();
+        assert!(!session.can_redo());
+        assert!(!session.redo());
+    }
+
+    #[test]
+    fn solving_consumes_every_hint() {
+        let crossmath = Crossmath::from_seed(13, 6).unwrap();
+        let solution = crossmath.solve(1).solution.unwrap();
+        let hints = crossmath.hints();
+        let mut session = GameSession::new(crossmath);
+        assert_eq!(session.hints(), hints);
+        assert_eq!(session.remaining_hints(), hints);
+
+        let (first, value) = solution[0];
+        session.set_cell(first, value).unwrap();
+        assert_eq!(session.consumed_hints(), [value]);
+        assert_eq!(session.consumed_mask().iter().filter(|&&consumed| consumed == 1).count(), 1);
+        assert_eq!(session.remaining_hints().len(), hints.len() - 1);
+
+        for (cell, value) in solution {
+            session.set_cell(cell, value).unwrap();
+        }
+        assert!(session.remaining_hints().is_empty());
+        assert!(session.check().is_solved());
+    }
+
+    #[test]
+    fn hints_lead_to_the_solution() {
+        let crossmath = Crossmath::with_config(&CrossmathConfig::from_difficulty(Difficulty::Expert).seed(2)).unwrap();
+        let hidden = crossmath.hidden_cells().len();
+        let mut session = GameSession::new(crossmath);
+
+        let next = session.next_hint_cell();
+        assert_eq!(session.hints_used(), 0);
+        let pointed = session.hint(HintTier::Cell).unwrap();
+        assert_eq!(next, Some(pointed.cell));
+        assert_eq!((pointed.equation, pointed.value), (None, None));
+        let explained = session.hint(HintTier::Equation).unwrap();
+        assert_eq!(explained.cell, pointed.cell);
+        let equation = session.crossmath().equations()[explained.equation.unwrap() as usize].clone();
+        assert!(equation.cells.contains(&pointed.cell));
+
+        // A wrong entry is pointed at again, until its value is revealed
+        session.set_cell(pointed.cell, 0).unwrap();
+        for _ in 0..hidden {
+            let revealed = session.hint(HintTier::Value).unwrap();
+            assert_eq!(session.entry(revealed.cell), revealed.value);
+        }
+        assert_eq!(session.hint(HintTier::Value), None);
+        assert!(session.check().is_solved());
+        assert_eq!(session.hints_used(), hidden as u32 + 2);
+        assert_eq!(session.hints_used_at(HintTier::Value), hidden as u32);
+    }
+
+    #[test]
+    fn clock_only_runs_when_resumed() {
+        let mut session = GameSession::new(Crossmath::from_seed(1, 4).unwrap());
+        assert_eq!(session.elapsed(5_000.0), 0.0);
+        session.resume(1_000.0);
+        session.resume(2_000.0);
+        assert_eq!(session.elapsed(4_000.0), 3_000.0);
+        session.pause(4_000.0);
+        assert!(session.is_paused());
+        assert_eq!(session.elapsed(60_000.0), 3_000.0);
+        session.resume(60_000.0);
+        assert_eq!(session.elapsed(61_000.0), 4_000.0);
+    }
+
+    #[test]
+    fn score_counts_mistakes_and_help() {
+        let crossmath = Crossmath::with_config(&CrossmathConfig::from_difficulty(Difficulty::Hard).seed(6)).unwrap();
+        let solution = crossmath.solve(1).solution.unwrap();
+        let hidden = solution.len() as u32;
+        let mut session = GameSession::new(crossmath);
+        session.resume(0.0);
+        for &(cell, value) in &solution {
+            session.set_cell(cell, value).unwrap();
+        }
+        assert!(session.check().is_solved());
+        let perfect = session.score(1_000.0);
+        assert_eq!(perfect, hidden * POINTS_PER_CELL * 2);
+
+        // A wrong value is only counted once, however many checks find it
+        let (cell, value) = solution[0];
+        session.set_cell(cell, value + 1).unwrap();
+        session.check();
+        session.check();
+        session.set_cell(cell, value).unwrap();
+        assert_eq!(session.wrong_entries(), 1);
+        assert_eq!(session.checks(), 3);
+        let penalties = WRONG_ENTRY_PENALTY + 2 * CHECK_PENALTY;
+        assert_eq!(session.score(1_000.0), perfect - 2 * penalties);
+
+        // Ten seconds over the time given cost a point
+        let late = hidden as f64 * SECONDS_PER_CELL * 1000.0 + 10_000.0;
+        assert_eq!(session.score(late), perfect - 2 * (penalties + 1));
+
+        // The weight is the one of the difficulty chosen, whatever the settings became
+        let config = CrossmathConfig::from_difficulty(Difficulty::Hard).hidden_ratio(0.3).seed(6);
+        let session = GameSession::new(Crossmath::with_config(&config).unwrap());
+        let hidden = session.crossmath().hidden_cells().len() as u32;
+        assert_eq!(session.score(0.0), hidden * POINTS_PER_CELL * 2);
+    }
+
+    #[test]
+    fn typing_a_number_is_not_a_mistake() {
+        let crossmath = Crossmath::with_config(&CrossmathConfig::from_difficulty(Difficulty::Medium).seed(4)).unwrap();
+        let solution = crossmath.solve(1).solution.unwrap();
+        let &(cell, value) = solution.iter().find(|(_, value)| *value >= 10).unwrap();
+        let mut session = GameSession::new(crossmath);
+
+        // The digits come one keystroke at a time
+        session.set_cell(cell, value / 10).unwrap();
+        session.set_cell(cell, value).unwrap();
+        session.check();
+        assert_eq!(session.wrong_entries(), 0);
+    }
+
+    #[test]
+    fn sessions_survive_a_round_trip() {
+        let mut session = GameSession::new(Crossmath::from_seed(17, 6).unwrap());
+        session.resume(0.0);
+        session.hint(HintTier::Value).unwrap();
+        let hidden = session.crossmath().hidden_cells()[0] as usize;
+        session.set_cell(hidden, 999).unwrap();
+
+        let json = session.to_json(12_000.0).unwrap();
+        let loaded = GameSession::from_json(&json).unwrap();
+        assert_eq!(loaded.crossmath().grid(), session.crossmath().grid());
+        assert_eq!(loaded.values(), session.values());
+        assert_eq!(loaded.hints_used(), 1);
+        assert_eq!(loaded.wrong_entries(), session.wrong_entries());
+        assert!(loaded.is_paused());
+        assert_eq!(loaded.elapsed(50_000.0), 12_000.0);
+        assert_eq!(loaded.score(50_000.0), session.score(12_000.0));
+
+        // Counters edited by hand can't overflow the score
+        let json = json
+            .replace("\"checks\":0", &format!("\"checks\":{}", u32::MAX))
+            .replace("\"wrong_entries\":0", &format!("\"wrong_entries\":{}", u32::MAX));
+        let mut loaded = GameSession::from_json(&json).unwrap();
+        assert_eq!(loaded.checks(), u32::MAX);
+        loaded.check();
+        loaded.hint(HintTier::Value).unwrap();
+        assert_eq!(loaded.checks(), u32::MAX);
+        assert_eq!(loaded.score(f64::MAX), 0);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::prelude::*;
+use crate::Crossmath;
+
+/// First byte of every share code, bumped when the encoding changes.
+const SHARE_VERSION: u8 = 1;
+
+/// URL-safe base64 alphabet, without padding.
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+const PRESETS: [Difficulty; 4] = [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard, Difficulty::Expert];
+const OPERATIONS: [Operation; 4] = [Operation::Plus, Operation::Minus, Operation::Times, Operation::Divide];
+
+const FLAG_PRESET: u8 = 1;
+const FLAG_CHAINED_HIDDEN: u8 = 1 << 1;
+const FLAG_MAXIMIZE_HIDDEN: u8 = 1 << 2;
+const FLAG_MAX_WIDTH: u8 = 1 << 3;
+const FLAG_MAX_HEIGHT: u8 = 1 << 4;
+const FLAG_OPERANDS: u8 = 1 << 5;
+/** Custom settings also tell the preset they started from */
+const FLAG_DIFFICULTY: u8 = 1 << 6;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ShareCodeError {
+    InvalidCharacter(char),
+    /** The code ends before every setting was read */
+    Truncated,
+    BadChecksum,
+    UnsupportedVersion(u8),
+    /** A byte doesn't stand for anything the decoder knows */
+    UnknownValue(u8),
+}
+
+impl fmt::Display for ShareCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShareCodeError::InvalidCharacter(character) => {
+                write!(f, "The character '{}' can't be part of a share code.", character)
+            }
+            ShareCodeError::Truncated => write!(f, "The share code is incomplete."),
+            ShareCodeError::BadChecksum => write!(f, "The share code was mistyped."),
+            ShareCodeError::UnsupportedVersion(version) => {
+                write!(f, "Share codes of version {} can't be read.", version)
+            }
+            ShareCodeError::UnknownValue(value) => write!(f, "Unknown value {} in the share code.", value),
+        }
+    }
+}
+
+impl std::error::Error for ShareCodeError {}
+
+impl Crossmath {
+    /// A short URL-safe code holding the seed and the config of the puzzle, enough
+    /// to generate the same grid again.
+    pub(crate) fn share_code(&self) -> String {
+        let config = &self.config;
+        let settings = &config.settings;
+        // The number of operands is written apart, so that a preset can be used with any
+        let preset = PRESETS.iter().position(|difficulty| {
+            DifficultySettings {
+                operands: settings.operands,
+                ..difficulty.settings()
+            } == *settings
+        });
+
+        let mut flags = 0;
+        if preset.is_some() {
+            flags |= FLAG_PRESET;
+        }
+        if settings.chained_hidden {
+            flags |= FLAG_CHAINED_HIDDEN;
+        }
+        if settings.maximize_hidden {
+            flags |= FLAG_MAXIMIZE_HIDDEN;
+        }
+        if config.max_width.is_some() {
+            flags |= FLAG_MAX_WIDTH;
+        }
+        if config.max_height.is_some() {
+            flags |= FLAG_MAX_HEIGHT;
+        }
+        if settings.operands != 2 {
+            flags |= FLAG_OPERANDS;
+        }
+        if preset.is_none() {
+            flags |= FLAG_DIFFICULTY;
+        }
+
+        let mut bytes = vec![SHARE_VERSION, flags];
+        write_varint(&mut bytes, self.seed());
+        write_varint(&mut bytes, config.equations as u64);
+        config.max_width.into_iter().for_each(|width| write_varint(&mut bytes, width as u64));
+        config.max_height.into_iter().for_each(|height| write_varint(&mut bytes, height as u64));
+        if settings.operands != 2 {
+            write_varint(&mut bytes, settings.operands as u64);
+        }
+
+        match preset {
+            Some(preset) => bytes.push(preset as u8),
+            None => {
+                write_varint(&mut bytes, settings.min_number as u64);
+                write_varint(&mut bytes, settings.max_number as u64);
+                write_varint(&mut bytes, settings.max_result as u64);
+                write_varint(&mut bytes, settings.max_intersections as u64);
+                bytes.extend(settings.hidden_ratio.to_le_bytes());
+                bytes.push(settings.operators.len() as u8);
+                for (operator, weight) in &settings.operators {
+                    let operator = OPERATIONS.iter().position(|known| known == operator).unwrap_or_default();
+                    bytes.push(operator as u8);
+                    write_varint(&mut bytes, *weight as u64);
+                }
+                let difficulty = PRESETS.iter().position(|known| *known == config.difficulty).unwrap_or_default();
+                bytes.push(difficulty as u8);
+            }
+        }
+
+        bytes.extend(checksum(&bytes).to_le_bytes());
+        encode(&bytes)
+    }
+
+    pub(crate) fn config_from_share_code(code: &str) -> Result<CrossmathConfig, ShareCodeError> {
+        let bytes = decode(code.trim().trim_start_matches('#'))?;
+        if bytes.len() < 2 {
+            return Err(ShareCodeError::Truncated);
+        }
+        let (bytes, sum) = bytes.split_at(bytes.len() - 2);
+        if checksum(bytes).to_le_bytes() != sum {
+            return Err(ShareCodeError::BadChecksum);
+        }
+
+        let mut reader = Reader { bytes, position: 0 };
+        let version = reader.byte()?;
+        if version != SHARE_VERSION {
+            return Err(ShareCodeError::UnsupportedVersion(version));
+        }
+        let flags = reader.byte()?;
+        let seed = reader.varint()?;
+        let mut config = CrossmathConfig::new().equations(reader.bounded(MAX_EQUATIONS)?).seed(seed);
+        if flags & FLAG_MAX_WIDTH != 0 {
+            config = config.max_width(reader.bounded(MAX_GRID_SIDE)?);
+        }
+        if flags & FLAG_MAX_HEIGHT != 0 {
+            config = config.max_height(reader.bounded(MAX_GRID_SIDE)?);
+        }
+        let operands = if flags & FLAG_OPERANDS != 0 { reader.bounded(MAX_OPERANDS)? } else { 2 };
+
+        let mut settings = if flags & FLAG_PRESET != 0 {
+            let preset = reader.byte()?;
+            config.difficulty = *PRESETS.get(preset as usize).ok_or(ShareCodeError::UnknownValue(preset))?;
+            config.difficulty.settings()
+        } else {
+            let mut settings = DifficultySettings {
+                min_number: reader.bounded(MAX_OPERAND)?,
+                max_number: reader.bounded(MAX_OPERAND)?,
+                max_result: reader.bounded(MAX_RESULT)?,
+                max_intersections: reader.bounded(MAX_EQUATIONS)?,
+                hidden_ratio: f64::from_le_bytes(reader.array()?),
+                operators: vec![],
+                ..DifficultySettings::default()
+            };
+            let count = reader.byte()?;
+            if count as usize > OPERATIONS.len() {
+                return Err(ShareCodeError::UnknownValue(count));
+            }
+            for _ in 0..count {
+                let operator = reader.byte()?;
+                let operator = *OPERATIONS
+                    .get(operator as usize)
+                    .ok_or(ShareCodeError::UnknownValue(operator))?;
+                settings.operators.push((operator, reader.bounded(MAX_WEIGHT)?));
+            }
+            if flags & FLAG_DIFFICULTY != 0 {
+                let preset = reader.byte()?;
+                config.difficulty = *PRESETS.get(preset as usize).ok_or(ShareCodeError::UnknownValue(preset))?;
+            }
+            settings
+        };
+        settings.chained_hidden = flags & FLAG_CHAINED_HIDDEN != 0;
+        settings.maximize_hidden = flags & FLAG_MAXIMIZE_HIDDEN != 0;
+        settings.operands = operands;
+
+        Ok(config.settings(settings))
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, ShareCodeError> {
+        let byte = *self.bytes.get(self.position).ok_or(ShareCodeError::Truncated)?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], ShareCodeError> {
+        let mut array = [0; N];
+        for byte in array.iter_mut() {
+            *byte = self.byte()?;
+        }
+        Ok(array)
+    }
+
+    /** LEB128 : 7 bits per byte, the highest bit tells whether more bytes follow */
+    fn varint(&mut self) -> Result<u64, ShareCodeError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(ShareCodeError::UnknownValue(0x80))
+    }
+
+    fn number(&mut self) -> Result<u32, ShareCodeError> {
+        let value = self.varint()?;
+        u32::try_from(value).map_err(|_| ShareCodeError::UnknownValue(0x80))
+    }
+
+    /** A number no bigger than max, hostile codes can't ask for huge grids */
+    fn bounded(&mut self, max: u32) -> Result<u32, ShareCodeError> {
+        let start = self.position;
+        let value = self.number()?;
+        if value > max {
+            return Err(ShareCodeError::UnknownValue(self.bytes[start]));
+        }
+        Ok(value)
+    }
+}
+
+fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/** Fletcher-16, catches mistyped and swapped characters */
+fn checksum(bytes: &[u8]) -> u16 {
+    let (mut low, mut high) = (0u16, 0u16);
+    for &byte in bytes {
+        low = (low + byte as u16) % 255;
+        high = (high + low) % 255;
+    }
+    (high << 8) | low
+}
+
+fn encode(bytes: &[u8]) -> String {
+    let mut code = String::new();
+    for chunk in bytes.chunks(3) {
+        let block = chunk.iter().enumerate().fold(0u32, |block, (i, &byte)| block | (byte as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            code.push(ALPHABET[((block >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    code
+}
+
+fn decode(code: &str) -> Result<Vec<u8>, ShareCodeError> {
+    let sextets: Vec<u32> = code
+        .chars()
+        .map(|character| {
+            ALPHABET
+                .iter()
+                .position(|&known| known as char == character)
+                .map(|position| position as u32)
+                .ok_or(ShareCodeError::InvalidCharacter(character))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut bytes = vec![];
+    for chunk in sextets.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(ShareCodeError::Truncated);
+        }
+        let block = chunk.iter().enumerate().fold(0u32, |block, (i, &sextet)| block | sextet << (18 - 6 * i));
+        for i in 0..chunk.len() - 1 {
+            bytes.push((block >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn share_codes_rebuild_the_same_grid() {
+        let config = CrossmathConfig::from_difficulty(Difficulty::Hard).equations(7).seed(987_654_321);
+        let entity = Crossmath::with_config(&config).unwrap();
+        let code = entity.to_share_code();
+        assert!(code.len() <= 24, "{}", code);
+        assert!(code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let shared = Crossmath::from_share_code(&code).unwrap();
+        assert_eq!(shared.grid(), entity.grid());
+        assert_eq!(shared.config(), entity.config());
+
+        let custom = config.operator_weights(3, 0, 1, 0).hidden_ratio(0.35).max_width(20).maximize_hidden(true);
+        let entity = Crossmath::with_config(&custom).unwrap();
+        let shared = Crossmath::from_share_code(&format!("#{}", entity.to_share_code())).unwrap();
+        assert_eq!(shared.config(), entity.config());
+        assert_eq!(shared.grid(), entity.grid());
+
+        let longer = CrossmathConfig::from_difficulty(Difficulty::Easy).operands(3).equations(5).seed(8);
+        let entity = Crossmath::with_config(&longer).unwrap();
+        let code = entity.to_share_code();
+        assert!(code.len() <= 24, "{}", code);
+        assert_eq!(Crossmath::from_share_code(&code).unwrap().grid(), entity.grid());
+    }
+
+    /// Checked against the wasm build, so that a code printed by the CLI opens the
+    /// same grid in the browser.
+    #[test]
+    fn share_codes_give_the_same_grid_on_every_target() {
+        let config = CrossmathConfig::from_difficulty(Difficulty::Hard).operands(3).equations(5).seed(2024);
+        assert_eq!(Crossmath::with_config(&config).unwrap().to_share_code(), "ASPoDwUDAia5");
+        let rendered = Crossmath::from_share_code("ASPoDwUDAia5").unwrap().render();
+        assert_eq!(rendered, " ; ; ; ; ; ; ; ; ; ;?; ; ; ; ; ; ; ; ; ; ; ; ; ; ; ; ;÷; ; ; ; ; ; ;?;-;69;÷;23;=;72; ; ; ;46;=;?;-;55;+;?; ; ; ; ; ; ;-; ; ; ;+; ; ; ; ; ; ; ; ; ; ; ; ;74; ; ; ;?; ; ; ; ; ; ; ; ; ; ; ; ;÷; ; ; ;=; ; ; ; ; ; ; ; ; ; ; ; ;37;=;?;+;27;-;?; ; ; ; ; ; ; ; ; ; ;=; ; ; ; ; ; ; ; ; ; ; ; ; ; ; ; ;?; ; ; ; ; ; ; ; ; ; ;92;75;88;13;25;29;19;70;");
+    }
+
+    #[test]
+    fn damaged_codes_are_rejected() {
+        let code = Crossmath::from_seed(42, 5).unwrap().to_share_code();
+        let error = |code: &str| Crossmath::from_share_code(code).err();
+
+        assert_eq!(error("AB+C"), Some(ShareCodeError::InvalidCharacter('+').into()));
+        assert_eq!(error(&code[..code.len() - 3]), Some(ShareCodeError::BadChecksum.into()));
+        assert_eq!(error("A"), Some(ShareCodeError::Truncated.into()));
+
+        let mut typo: Vec<char> = code.chars().collect();
+        typo[4] = if typo[4] == 'A' { 'B' } else { 'A' };
+        let typo: String = typo.into_iter().collect();
+        assert_eq!(error(&typo), Some(ShareCodeError::BadChecksum.into()));
+
+        // Valid checksums, but 100000 and 2000 equations
+        assert!(matches!(Crossmath::config_from_share_code("AQMFoI0GAT5q"), Err(ShareCodeError::UnknownValue(_))));
+        assert!(matches!(Crossmath::config_from_share_code("AQMF0A8B6bo"), Err(ShareCodeError::UnknownValue(_))));
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::BTreeMap;
+
+use crate::prelude::*;
+
+/// Solutions looked for when no maximum is given, enough to tell whether a puzzle
+/// has a single solution.
+const DEFAULT_MAX_SOLUTIONS: u32 = 2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Step {
+    /// The only unknown term of an equation was computed
+    Deduced { cell: usize, value: u32, equation: usize },
+    /// No deduction was left, a value of the hint pool is tried for a cell
+    Guessed { cell: usize, value: u32 },
+    /// An equation can't hold anymore, the last guess was wrong
+    Contradiction { equation: usize },
+    /// Every hidden cell has a value satisfying all the equations
+    Solved,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SolverReport {
+    /// Value of each hidden cell in the first solution found, in grid order
+    pub solution: Option<Vec<(usize, u32)>>,
+    /// Number of solutions found, up to the maximum asked for
+    pub solutions: u32,
+    /** Empty when the trace was turned off */
+    pub trace: Vec<Step>,
+    /** Guesses tried, see `Solver::max_nodes` */
+    pub nodes: u32,
+    /// The search stopped at the node limit, more solutions may exist
+    pub exhausted: bool,
+}
+
+impl SolverReport {
+    /** A single solution, and a search that went to its end to make sure of it */
+    pub fn is_unique(&self) -> bool {
+        self.solutions == 1 && !self.exhausted
+    }
+}
+
+/// Fills the hidden cells of a grid with the values of the hint pool, so that
+/// every equation holds.
+pub struct Solver<'a> {
+    grid: &'a Grid,
+    /** What each equation reads, `None` when its operators are missing from the grid */
+    expressions: Vec<Option<Expression>>,
+    max_solutions: u32,
+    max_nodes: Option<u32>,
+    trace: bool,
+}
+
+/// The cells of the terms of an equation, and its operators, read once from the grid.
+struct Expression {
+    /** Operands then the result, as in `PlacedEquation::numbers` */
+    numbers: Vec<usize>,
+    operators: Vec<Operation>,
+}
+
+impl Expression {
+    fn result_cell(&self) -> usize {
+        self.numbers[self.numbers.len() - 1]
+    }
+
+    fn operands(&self, values: &[Option<u32>]) -> Vec<Option<u32>> {
+        self.numbers[..self.numbers.len() - 1].iter().map(|&cell| values[cell]).collect()
+    }
+
+    /** Whether the equation holds, `None` while a term is unknown */
+    fn holds(&self, values: &[Option<u32>]) -> Option<bool> {
+        let operands = self.operands(values).into_iter().collect::<Option<Vec<u32>>>()?;
+        Some(evaluate(&operands, &self.operators) == Some(values[self.result_cell()]?))
+    }
+
+    /// The cell of the only unknown term, with the value making the equation hold
+    /// if there is one.
+    fn deduce(&self, values: &[Option<u32>]) -> Option<(usize, Option<u32>)> {
+        let mut unknowns = self.numbers.iter().filter(|&&cell| values[cell].is_none());
+        let (Some(&cell), None) = (unknowns.next(), unknowns.next()) else {
+            return None;
+        };
+        let operands = self.operands(values);
+        let value = match values[self.result_cell()] {
+            None => evaluate(&operands.into_iter().collect::<Option<Vec<u32>>>()?, &self.operators),
+            Some(result) => solve_operand(&operands, &self.operators, result),
+        };
+        Some((cell, value))
+    }
+}
+
+/// Values known so far, and hints left to place
+#[derive(Clone)]
+struct State {
+    values: Vec<Option<u32>>,
+    pool: BTreeMap<u32, u32>,
+}
+
+impl State {
+    fn place(&mut self, cell: usize, value: u32) -> bool {
+        match self.pool.get_mut(&value) {
+            Some(count) => {
+                *count -= 1;
+                if *count == 0 {
+                    self.pool.remove(&value);
+                }
+                self.values[cell] = Some(value);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<'a> Solver<'a> {
+    pub fn new(grid: &'a Grid, equations: &'a [PlacedEquation]) -> Self {
+        let expressions = equations
+            .iter()
+            .map(|eq| {
+                let operators = eq.operators(grid)?;
+                Some(Expression { numbers: eq.numbers(), operators })
+            })
+            .collect();
+        Self {
+            grid,
+            expressions,
+            max_solutions: DEFAULT_MAX_SOLUTIONS,
+            max_nodes: None,
+            trace: true,
+        }
+    }
+
+    pub fn max_solutions(mut self, max_solutions: u32) -> Self {
+        self.max_solutions = max_solutions.max(1);
+        self
+    }
+
+    /// Stops the search after this many guesses, without a limit by default. An
+    /// exhausted search can't tell whether the solution is unique.
+    pub fn max_nodes(mut self, max_nodes: u32) -> Self {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    /** Whether every step is recorded in the report, on by default */
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    pub fn solve(&self) -> SolverReport {
+        let mut report = SolverReport {
+            solution: None,
+            solutions: 0,
+            trace: vec![],
+            nodes: 0,
+            exhausted: false,
+        };
+        self.search(self.initial_state(), &mut report);
+        report
+    }
+
+    /// The deduction a player would find the most easily : an equation with a single
+    /// unknown term, preferring additions to divisions and results to operands.
+    pub fn easiest_deduction(&self) -> Option<Step> {
+        let state = self.initial_state();
+        self.expressions
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, expression)| {
+                let expression = expression.as_ref()?;
+                let (cell, value) = expression.deduce(&state.values)?;
+                let difficulty: u32 = expression
+                    .operators
+                    .iter()
+                    .map(|operator| match operator {
+                        Operation::Plus => 0,
+                        Operation::Minus => 2,
+                        Operation::Times => 4,
+                        Operation::Divide => 6,
+                    })
+                    .sum();
+                let cost = u32::from(cell != expression.result_cell());
+                Some((difficulty + cost, Step::Deduced { cell, value: value?, equation: idx }))
+            })
+            .min_by_key(|(cost, _)| *cost)
+            .map(|(_, step)| step)
+    }
+
+    fn initial_state(&self) -> State {
+        let mut state = State {
+            values: self
+                .grid
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Number { value, hidden: false } => Some(*value),
+                    _ => None,
+                })
+                .collect(),
+            pool: BTreeMap::new(),
+        };
+        for cell in self.grid.iter().filter(|cell| cell.is_hidden()) {
+            if let Some(value) = cell.value() {
+                *state.pool.entry(value).or_insert(0) += 1;
+            }
+        }
+        state
+    }
+
+    fn search(&self, mut state: State, report: &mut SolverReport) {
+        if !self.propagate(&mut state, report) {
+            return;
+        }
+
+        let Some(cell) = self.next_unknown(&state) else {
+            self.record(report, Step::Solved);
+            report.solutions += 1;
+            if report.solution.is_none() {
+                let solution = (0..self.grid.len())
+                    .filter(|&idx| self.grid[idx].is_hidden())
+                    .filter_map(|idx| state.values[idx].map(|value| (idx, value)))
+                    .collect();
+                report.solution = Some(solution);
+            }
+            return;
+        };
+
+        let candidates: Vec<u32> = state.pool.keys().copied().collect();
+        for value in candidates {
+            if report.solutions >= self.max_solutions || report.exhausted {
+                return;
+            }
+            if self.max_nodes.is_some_and(|max_nodes| report.nodes >= max_nodes) {
+                report.exhausted = true;
+                return;
+            }
+            report.nodes += 1;
+            self.record(report, Step::Guessed { cell, value });
+            let mut next = state.clone();
+            next.place(cell, value);
+            self.search(next, report);
+        }
+    }
+
+    /// Applies every deduction available, returns false when an equation
+    /// can't hold anymore.
+    fn propagate(&self, state: &mut State, report: &mut SolverReport) -> bool {
+        loop {
+            let mut progress = false;
+            for (idx, expression) in self.expressions.iter().enumerate() {
+                let Some(expression) = expression else {
+                    continue;
+                };
+                if expression.holds(&state.values) == Some(false) {
+                    self.record(report, Step::Contradiction { equation: idx });
+                    return false;
+                }
+                match expression.deduce(&state.values) {
+                    Some((cell, Some(value))) if state.place(cell, value) => {
+                        self.record(report, Step::Deduced {
+                            cell,
+                            value,
+                            equation: idx,
+                        });
+                        progress = true;
+                    }
+                    Some(_) => {
+                        self.record(report, Step::Contradiction { equation: idx });
+                        return false;
+                    }
+                    None => {}
+                }
+            }
+            if !progress {
+                return true;
+            }
+        }
+    }
+
+    fn record(&self, report: &mut SolverReport, step: Step) {
+        if self.trace {
+            report.trace.push(step);
+        }
+    }
+
+    /** An unknown cell from the equation with the fewest unknowns */
+    fn next_unknown(&self, state: &State) -> Option<usize> {
+        self.expressions
+            .iter()
+            .flatten()
+            .map(|expression| {
+                expression
+                    .numbers
+                    .iter()
+                    .copied()
+                    .filter(|&cell| state.values[cell].is_none())
+                    .collect::<Vec<usize>>()
+            })
+            .filter(|unknowns| !unknowns.is_empty())
+            .min_by_key(|unknowns| unknowns.len())
+            .map(|unknowns| unknowns[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Crossmath;
+
+    #[test]
+    fn generated_puzzles_are_solved() {
+        for seed in 0..50 {
+            let entity = Crossmath::from_seed(seed, 6).unwrap();
+            let report = entity.solve(1);
+            assert_eq!(report.solutions, 1, "seed {}", seed);
+            let solution = report.solution.unwrap();
+            assert_eq!(solution.len(), entity.hints().len());
+            let mut values: Vec<Option<u32>> = entity.grid().iter().map(|cell| cell.value()).collect();
+            for &(cell, value) in &solution {
+                values[cell] = Some(value);
+            }
+            for eq in entity.equations() {
+                assert_eq!(eq.holds(entity.grid(), &values), Some(true), "{:?}", entity);
+            }
+        }
+    }
+
+    #[test]
+    fn ambiguous_puzzles_are_detected() {
+        let (grid, equations) = Grid::sample(5, 1, vec![(0, Equation::new(5, 7, Operation::Plus).unwrap())], &[0, 2]);
+
+        let report = Solver::new(&grid, &equations).max_solutions(10).solve();
+        assert_eq!(report.solutions, 2);
+        assert!(!report.is_unique());
+        assert!(report.trace.contains(&Step::Guessed { cell: 0, value: 5 }));
+        assert!(report.trace.contains(&Step::Deduced { cell: 2, value: 7, equation: 0 }));
+        assert_eq!(report.nodes, 2);
+        assert!(!report.exhausted);
+
+        // Out of budget before the second solution, uniqueness can't be told
+        let report = Solver::new(&grid, &equations).max_nodes(1).trace(false).solve();
+        assert_eq!(report.solutions, 1);
+        assert!(report.exhausted);
+        assert!(!report.is_unique());
+        assert!(report.trace.is_empty());
+    }
+
+    #[test]
+    fn easiest_deduction_prefers_simple_operations() {
+        let equations = vec![
+            (0, Equation::new(12, 3, Operation::Divide).unwrap()),
+            (10, Equation::new(5, 7, Operation::Plus).unwrap()),
+        ];
+        let (mut grid, equations) = Grid::sample(5, 3, equations, &[4, 10]);
+
+        let solver = Solver::new(&grid, &equations);
+        assert_eq!(solver.easiest_deduction(), Some(Step::Deduced { cell: 10, value: 5, equation: 1 }));
+
+        grid.hide(12);
+        let solver = Solver::new(&grid, &equations);
+        assert_eq!(solver.easiest_deduction(), Some(Step::Deduced { cell: 4, value: 4, equation: 0 }));
+    }
+
+    #[test]
+    fn deductions_need_no_guess() {
+        let (grid, equations) = Grid::sample(5, 1, vec![(0, Equation::new(12, 3, Operation::Divide).unwrap())], &[4]);
+
+        let report = Solver::new(&grid, &equations).solve();
+        assert!(report.is_unique());
+        assert_eq!(report.solution, Some(vec![(4, 4)]));
+        assert_eq!(
+            report.trace,
+            [Step::Deduced { cell: 4, value: 4, equation: 0 }, Step::Solved]
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use crate::prelude::*;
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CellKind {
+    Empty,
+    Number,
+    Operator,
+    Equals,
+}
+
+/// A cell of the grid as seen from JS.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CellView {
+    pub x: u32,
+    pub y: u32,
+    pub kind: CellKind,
+    /// The number of the cell, even when it is hidden to the player
+    pub value: Option<u32>,
+    pub hidden: bool,
+    operator: Option<Operation>,
+}
+
+#[wasm_bindgen]
+impl CellView {
+    /** What is written in the cell, "?" when it is hidden */
+    pub fn symbol(&self) -> String {
+        self.cell().to_string()
+    }
+}
+
+impl CellView {
+    pub fn new(grid: &Grid, idx: usize) -> Self {
+        let cell = grid[idx];
+        let (x, y) = grid.coordinates(idx);
+        let kind = match cell {
+            Cell::Empty => CellKind::Empty,
+            Cell::Number { .. } => CellKind::Number,
+            Cell::Operator(_) => CellKind::Operator,
+            Cell::Equals => CellKind::Equals,
+        };
+        let operator = match cell {
+            Cell::Operator(operator) => Some(operator),
+            _ => None,
+        };
+        Self {
+            x,
+            y,
+            kind,
+            value: cell.value(),
+            hidden: cell.is_hidden(),
+            operator,
+        }
+    }
+
+    pub fn cell(&self) -> Cell {
+        match (self.kind, self.value, self.operator) {
+            (CellKind::Number, Some(value), _) => Cell::Number { value, hidden: self.hidden },
+            (CellKind::Operator, _, Some(operator)) => Cell::Operator(operator),
+            (CellKind::Equals, _, _) => Cell::Equals,
+            _ => Cell::Empty,
+        }
+    }
+}
+
+/// An equation of the grid as seen from JS, cells are given by index in the grid.
+#[wasm_bindgen]
+#[derive(Clone, PartialEq, Debug)]
+pub struct EquationView {
+    pub start: u32,
+    pub direction: Direction,
+    /** The equation this one was linked to when it was placed */
+    pub linked_to: Option<u32>,
+    /** The term of this equation shared with the one it was linked to, by index in `terms` */
+    pub term: Option<u32>,
+    cells: Vec<u32>,
+    terms: Vec<u32>,
+    crossings: Vec<u32>,
+    operators: Vec<Operation>,
+}
+
+#[wasm_bindgen]
+impl EquationView {
+    /** Every cell of the equation, from the start position */
+    pub fn cells(&self) -> Vec<u32> {
+        self.cells.clone()
+    }
+
+    /** Cells of the operands in the order they are computed, then of the result */
+    pub fn terms(&self) -> Vec<u32> {
+        self.terms.clone()
+    }
+
+    /** Equations sharing a cell with this one */
+    pub fn crossings(&self) -> Vec<u32> {
+        self.crossings.clone()
+    }
+
+    /** Symbols of the operators, in the order they are computed */
+    pub fn operators(&self) -> Vec<String> {
+        self.operators.iter().map(|operator| operator.symbol().to_string()).collect()
+    }
+}
+
+impl EquationView {
+    pub fn new(grid: &Grid, eq: &PlacedEquation) -> Self {
+        let operators = eq
+            .operators(grid)
+            .expect("Every other cell of an equation holds an operator");
+        Self {
+            start: eq.start as u32,
+            direction: eq.direction,
+            linked_to: eq.link.map(|(owner, _)| owner as u32),
+            term: eq.link.map(|(_, term)| term as u32),
+            cells: eq.cells.iter().map(|&cell| cell as u32).collect(),
+            terms: eq.numbers().into_iter().map(|cell| cell as u32).collect(),
+            crossings: eq.crossings.iter().map(|&other| other as u32).collect(),
+            operators,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn views_describe_the_grid() {
+        let (grid, equations) = Grid::sample(5, 1, vec![(0, Equation::new(12, 3, Operation::Divide).unwrap())], &[2]);
+
+        let views: Vec<CellView> = (0..grid.len()).map(|idx| CellView::new(&grid, idx)).collect();
+        assert_eq!(views[1].kind, CellKind::Operator);
+        assert_eq!(views[1].symbol(), "÷");
+        assert_eq!((views[2].value, views[2].hidden), (Some(3), true));
+        assert_eq!(views[2].symbol(), "?");
+        assert!(views.iter().enumerate().all(|(idx, view)| view.cell() == grid[idx]));
+
+        let view = EquationView::new(&grid, &equations[0]);
+        assert_eq!(view.cells(), [0, 1, 2, 3, 4]);
+        assert_eq!(view.terms(), [0, 2, 4]);
+        assert_eq!(view.operators(), ["÷"]);
+    }
+}
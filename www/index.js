@@ -1,69 +1,200 @@
-import { Crossmath } from "wasm-crossmath";
+import { CellKind, Crossmath, CrossmathConfig, Difficulty, GameSession, HintTier } from "wasm-crossmath";
+
+let session;
+let share_code;
+// The last hint given, asking again for the same cell tells more
+let last_hint;
+let solved = false;
+
+function start_session(crossmath) {
+    share_code = crossmath.to_share_code();
+    session = new GameSession(crossmath);
+    session.resume(Date.now());
+    last_hint = undefined;
+    solved = false;
+    history.replaceState(null, "", "#" + share_code);
+    show_grid(session.puzzle());
+}
 
 function generate_grid() {
-    const crossmath = Crossmath.new(6);
-    const width = crossmath.width();
-    const height = crossmath.height();
-    const grid = crossmath.render();
-
-    document.querySelector('#checkBtn').addEventListener("click", () => {
-        let answer = build_answer();
-        console.log(answer);
-        let won = crossmath.check(answer);
-        let message = document.querySelector("#message");
-        message.textContent = won ? "Bravo ! Tu as gagné !" : "Oops ! Il semble qu'il y a des erreurs dans ta grille..."
-        let dialog = document.querySelector("#result-dialog");
-        dialog.showModal();
-    })
+    const difficulty = document.querySelector("#difficulty").value;
+    const config = CrossmathConfig.from_difficulty(Difficulty[difficulty]).equations(6);
+    try {
+        start_session(Crossmath.with_config(config));
+    } catch (error) {
+        console.error(error.message);
+    }
+}
 
-    show_grid(grid, width, height)
+function load_shared_grid() {
+    const code = location.hash.slice(1);
+    if (!code) {
+        return false;
+    }
+    try {
+        start_session(Crossmath.from_share_code(code));
+    } catch (error) {
+        console.error(error.message);
+        return false;
+    }
+    return true;
 }
 
-function show_grid(grid, width, height) {
+function show_grid(crossmath) {
     let board = document.querySelector("#board");
     board.innerHTML = "";
-    let grid_len = width * height;
-    for(const value of grid.split(";").slice(0, grid_len)) {
+    crossmath.cells().forEach((cell, idx) => {
         let block = document.createElement("div");
         block.classList.add("block");
-        if(value === "?") {
+        if(cell.hidden) {
             block.classList.add("hidden");
             block.setAttribute("contenteditable", true);
-        } else if(value != " " && value != "\n" && value != "\0") {
+            block.addEventListener("input", () => {
+                const value = parseInt(block.textContent.trim(), 10);
+                if (Number.isNaN(value)) {
+                    session.clear_cell(idx);
+                } else {
+                    session.set_cell(idx, value);
+                }
+                show_hints();
+            })
+        } else if(cell.kind !== CellKind.Empty) {
             block.classList.add("filled");
-            block.textContent = value;
+            block.textContent = cell.symbol();
         }
         board.appendChild(block)
-    }
-    board.style.setProperty("--grid-width", width.toString())
+    })
+    board.style.setProperty("--grid-width", crossmath.width().toString())
+    show_hints();
+}
 
+function show_hints() {
     let options = document.querySelector("#options div")
     options.innerHTML = "";
-    for(const value of grid.split(";").slice(grid_len, -1)) {
+    const consumed = session.consumed_mask();
+    session.hints().forEach((value, idx) => {
         let block = document.createElement("div");
         block.classList.add("block");
         block.classList.add("option");
+        if (consumed[idx]) {
+            block.classList.add("used");
+        }
         block.textContent = value;
         options.appendChild(block)
+    })
+}
+
+/* Writes the entries of the session back in the grid, after an undo or a redo */
+function show_entries() {
+    const blocks = document.querySelector("#board").children;
+    for (const idx of session.hidden_cells()) {
+        blocks[idx].textContent = session.entry(idx) ?? "";
     }
+    show_hints();
 }
 
-function build_answer() {
-    let board = document.querySelector("#board");
-    let answer = "";
-    for(const block of board.children) {
-        answer += (block.textContent) ? block.textContent : " ";
-        answer += ";"
+function show_hint(hint) {
+    const blocks = document.querySelector("#board").children;
+    for (const block of blocks) {
+        block.classList.remove("hinted", "hinted-equation");
+    }
+    if (hint.equation !== undefined) {
+        const equation = session.equations()[hint.equation];
+        for (const idx of equation.cells()) {
+            blocks[idx].classList.add("hinted-equation");
+        }
+    }
+    blocks[hint.cell].classList.add("hinted");
+    if (hint.value !== undefined) {
+        show_entries();
     }
-    return answer;
 }
 
-generate_grid();
+function show_mistakes(report) {
+    let blocks = document.querySelector("#board").children;
+    for (const block of blocks) {
+        block.classList.remove("wrong");
+    }
+    for (const idx of report.wrong_cells()) {
+        blocks[idx].classList.add("wrong");
+    }
+}
+
+if (!load_shared_grid()) {
+    generate_grid();
+}
+
+window.addEventListener("hashchange", () => {
+    if (location.hash.slice(1) !== share_code) {
+        load_shared_grid();
+    }
+})
+
+document.addEventListener("visibilitychange", () => {
+    if (document.hidden) {
+        session.pause(Date.now());
+    } else if (!solved) {
+        session.resume(Date.now());
+    }
+})
+
+document.addEventListener("keydown", (event) => {
+    if (!(event.ctrlKey || event.metaKey)) {
+        return;
+    }
+    const key = event.key.toLowerCase();
+    const undo = key === "z" && !event.shiftKey;
+    const redo = key === "y" || (key === "z" && event.shiftKey);
+    if ((undo && session.undo()) || (redo && session.redo())) {
+        event.preventDefault();
+        show_entries();
+    }
+})
+
+document.querySelector('#hintBtn').addEventListener("click", () => {
+    // A different cell starts again from the lightest hint
+    let tier = HintTier.Cell;
+    if (last_hint !== undefined && last_hint.tier !== HintTier.Value
+        && last_hint.cell === session.next_hint_cell()) {
+        tier = last_hint.tier + 1;
+    }
+    const hint = session.hint(tier);
+    if (hint === undefined) {
+        return;
+    }
+    last_hint = hint;
+    show_hint(hint);
+})
+
+document.querySelector('#checkBtn').addEventListener("click", () => {
+    const report = session.check();
+    show_mistakes(report);
+    let message = document.querySelector("#message");
+    if (report.is_solved()) {
+        const now = Date.now();
+        session.pause(now);
+        solved = true;
+        const seconds = Math.round(session.elapsed(now) / 1000);
+        const time = `${Math.floor(seconds / 60)} min ${seconds % 60} s`;
+        message.textContent = `Bravo ! Tu as gagné en ${time}, avec ${session.score(now)} points !`;
+    } else {
+        const wrong = report.wrong_cells().length;
+        const empty = report.empty_cells().length;
+        message.textContent = `Grille complétée à ${Math.round(report.completion())} %. `
+            + `${wrong} case(s) fausse(s), ${empty} case(s) vide(s).`;
+    }
+    let dialog = document.querySelector("#result-dialog");
+    dialog.showModal();
+})
 
 document.querySelector('#newGridBtn').addEventListener("click", () => {
     generate_grid();
 })
 
+document.querySelector('#difficulty').addEventListener("change", () => {
+    generate_grid();
+})
+
 document.querySelector('#helpBtn').addEventListener("click", () => {
     let dialog = document.querySelector("#help-dialog");
     dialog.showModal();